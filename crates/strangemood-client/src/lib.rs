@@ -0,0 +1,215 @@
+//! Client helpers for Strangemood license airdrops.
+//!
+//! [`Airdrop`] builds the Merkle tree a `Distributor` commits to, from a list of
+//! wallets and quantities imported off-chain, and generates the `init_distributor`
+//! and `claim_license` instructions and transactions that go with it.
+
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::{
+    get_associated_token_address, spl_associated_token_account::instruction as ata_instruction,
+};
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::system_program;
+use solana_sdk::transaction::Transaction;
+use strangemood_revival::{airdrop_leaf, verify_merkle_proof};
+
+/// A Merkle tree hashed the way the program verifies it: sorted pairs, with an
+/// odd node promoted to the next layer as is.
+#[derive(Clone, Debug)]
+pub struct MerkleTree {
+    layers: Vec<Vec<[u8; 32]>>,
+}
+
+impl MerkleTree {
+    pub fn new(leaves: Vec<[u8; 32]>) -> Self {
+        let mut layers = vec![leaves];
+        while layers.last().unwrap().len() > 1 {
+            let next = layers
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] if a <= b => hash_pair(a, b),
+                    [a, b] => hash_pair(b, a),
+                    [a] => *a,
+                    _ => unreachable!(),
+                })
+                .collect();
+            layers.push(next);
+        }
+        MerkleTree { layers }
+    }
+
+    /// The root, or all zeroes for an empty tree.
+    pub fn root(&self) -> [u8; 32] {
+        self.layers
+            .last()
+            .and_then(|layer| layer.first())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn proof(&self, index: usize) -> Vec<[u8; 32]> {
+        let mut proof = vec![];
+        let mut position = index;
+        for layer in &self.layers[..self.layers.len() - 1] {
+            if let Some(sibling) = layer.get(position ^ 1) {
+                proof.push(*sibling);
+            }
+            position /= 2;
+        }
+        proof
+    }
+}
+
+fn hash_pair(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    solana_sdk::hash::hashv(&[a, b]).to_bytes()
+}
+
+/// One wallet's allocation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AirdropEntry {
+    pub wallet: Pubkey,
+    pub quantity: u64,
+}
+
+/// An airdrop of licenses for one listing, claimable through a `Distributor`
+/// at `[b"distributor", listing, distributor_id]`.
+#[derive(Clone, Debug)]
+pub struct Airdrop {
+    pub listing_mint: Pubkey,
+    pub listing: Pubkey,
+    pub distributor_id: u64,
+    pub entries: Vec<AirdropEntry>,
+    tree: MerkleTree,
+}
+
+impl Airdrop {
+    pub fn new(listing_mint: Pubkey, distributor_id: u64, entries: Vec<AirdropEntry>) -> Self {
+        let (listing, _) = Pubkey::find_program_address(
+            &[b"listing", listing_mint.as_ref()],
+            &strangemood_revival::ID,
+        );
+        let leaves = entries
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| {
+                airdrop_leaf(index as u32, &entry.wallet, &listing, entry.quantity)
+            })
+            .collect();
+        Airdrop {
+            listing_mint,
+            listing,
+            distributor_id,
+            entries,
+            tree: MerkleTree::new(leaves),
+        }
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.tree.root()
+    }
+
+    pub fn distributor(&self) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[
+                b"distributor",
+                self.listing.as_ref(),
+                &self.distributor_id.to_le_bytes(),
+            ],
+            &strangemood_revival::ID,
+        )
+    }
+
+    /// The index of `wallet`'s first allocation.
+    pub fn position(&self, wallet: &Pubkey) -> Option<u32> {
+        self.entries
+            .iter()
+            .position(|entry| entry.wallet == *wallet)
+            .map(|index| index as u32)
+    }
+
+    pub fn proof(&self, index: u32) -> Vec<[u8; 32]> {
+        self.tree.proof(index as usize)
+    }
+
+    /// Checks a claim against the root exactly as `claim_license` does.
+    pub fn verify(&self, index: u32) -> bool {
+        let entry = &self.entries[index as usize];
+        let leaf = airdrop_leaf(index, &entry.wallet, &self.listing, entry.quantity);
+        verify_merkle_proof(&self.proof(index), &self.root(), leaf)
+    }
+
+    /// `init_distributor`, signed by the listing authority.
+    pub fn init_instruction(&self, authority: &Pubkey) -> Instruction {
+        let (distributor, distributor_bump) = self.distributor();
+        Instruction {
+            program_id: strangemood_revival::ID,
+            accounts: strangemood_revival::accounts::InitDistributor {
+                distributor,
+                listing: self.listing,
+                user: *authority,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: strangemood_revival::instruction::InitDistributor {
+                _distributor_bump: distributor_bump,
+                distributor_id: self.distributor_id,
+                root: self.root(),
+                max_claims: self.entries.len() as u32,
+            }
+            .data(),
+        }
+    }
+
+    /// Creates the wallet's associated license account if needed, then claims
+    /// allocation `index` into it.
+    pub fn claim_instructions(&self, index: u32) -> Vec<Instruction> {
+        let entry = &self.entries[index as usize];
+        let token_program = anchor_spl::token::ID;
+        let license = get_associated_token_address(&entry.wallet, &self.listing_mint);
+        let (listing_mint_authority, listing_mint_bump) = Pubkey::find_program_address(
+            &[b"mint", self.listing_mint.as_ref()],
+            &strangemood_revival::ID,
+        );
+
+        let create_license = ata_instruction::create_associated_token_account_idempotent(
+            &entry.wallet,
+            &entry.wallet,
+            &self.listing_mint,
+            &token_program,
+        );
+        let claim = Instruction {
+            program_id: strangemood_revival::ID,
+            accounts: strangemood_revival::accounts::ClaimLicense {
+                distributor: self.distributor().0,
+                listing: self.listing,
+                listing_mint: self.listing_mint,
+                listing_mint_authority,
+                listing_token_account: license,
+                claimant: entry.wallet,
+                token_program,
+            }
+            .to_account_metas(None),
+            data: strangemood_revival::instruction::ClaimLicense {
+                listing_mint_bump,
+                index,
+                quantity: entry.quantity,
+                proof: self.proof(index),
+            }
+            .data(),
+        };
+        vec![create_license, claim]
+    }
+
+    /// An unsigned claim transaction paid for by the claiming wallet.
+    pub fn claim_transaction(&self, index: u32, recent_blockhash: Hash) -> Transaction {
+        let wallet = self.entries[index as usize].wallet;
+        let mut transaction =
+            Transaction::new_with_payer(&self.claim_instructions(index), Some(&wallet));
+        transaction.message.recent_blockhash = recent_blockhash;
+        transaction
+    }
+}
@@ -0,0 +1,77 @@
+use anchor_lang::Discriminator;
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use strangemood_client::{Airdrop, AirdropEntry, MerkleTree};
+use strangemood_revival::{airdrop_leaf, verify_merkle_proof};
+
+fn airdrop(len: u8) -> Airdrop {
+    let entries = (0..len)
+        .map(|i| AirdropEntry {
+            wallet: Pubkey::new_from_array([i + 1; 32]),
+            quantity: i as u64 + 1,
+        })
+        .collect();
+    Airdrop::new(Pubkey::new_from_array([200; 32]), 1, entries)
+}
+
+#[test]
+fn every_allocation_verifies() {
+    for len in 1..=9 {
+        let airdrop = airdrop(len);
+        for index in 0..len as u32 {
+            assert!(airdrop.verify(index), "{} of {}", index, len);
+        }
+    }
+}
+
+#[test]
+fn single_leaf_tree_is_its_own_root() {
+    let leaf = [9; 32];
+    let tree = MerkleTree::new(vec![leaf]);
+    assert_eq!(tree.root(), leaf);
+    assert!(tree.proof(0).is_empty());
+    assert_eq!(MerkleTree::new(vec![]).root(), [0; 32]);
+}
+
+#[test]
+fn altered_allocation_is_rejected() {
+    let airdrop = airdrop(5);
+    let entry = airdrop.entries[2];
+    let proof = airdrop.proof(2);
+    let root = airdrop.root();
+
+    let inflated = airdrop_leaf(2, &entry.wallet, &airdrop.listing, entry.quantity + 1);
+    assert!(!verify_merkle_proof(&proof, &root, inflated));
+
+    let stranger = Pubkey::new_unique();
+    let stolen = airdrop_leaf(2, &stranger, &airdrop.listing, entry.quantity);
+    assert!(!verify_merkle_proof(&proof, &root, stolen));
+
+    let moved = airdrop_leaf(3, &entry.wallet, &airdrop.listing, entry.quantity);
+    assert!(!verify_merkle_proof(&proof, &root, moved));
+}
+
+#[test]
+fn claim_transaction_is_paid_and_signed_by_the_wallet() {
+    let airdrop = airdrop(3);
+    let wallet = airdrop.entries[1].wallet;
+    assert_eq!(airdrop.position(&wallet), Some(1));
+
+    let instructions = airdrop.claim_instructions(1);
+    assert_eq!(instructions.len(), 2);
+    let claim = &instructions[1];
+    assert_eq!(claim.program_id, strangemood_revival::ID);
+    assert_eq!(
+        &claim.data[..8],
+        strangemood_revival::instruction::ClaimLicense::DISCRIMINATOR
+    );
+    assert!(claim
+        .accounts
+        .iter()
+        .any(|meta| meta.pubkey == wallet && meta.is_signer));
+    assert_eq!(claim.accounts[0].pubkey, airdrop.distributor().0);
+
+    let transaction = airdrop.claim_transaction(1, Hash::default());
+    assert_eq!(transaction.message.account_keys[0], wallet);
+    assert_eq!(transaction.message.header.num_required_signatures, 1);
+}
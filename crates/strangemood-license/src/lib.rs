@@ -0,0 +1,248 @@
+//! Off-chain license verification for game servers.
+//!
+//! A server hands the player a [`LoginChallenge`], the player signs
+//! [`LoginChallenge::message`] with their wallet, and the server checks the
+//! signature together with the `Listing`, listing mint, license token account
+//! and revocation record it fetched over RPC. No RPC client or validator is
+//! needed; accounts are decoded with the program's own deserializers.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::AccountDeserialize;
+use anchor_spl::token::{Mint, TokenAccount};
+use ed25519_dalek::{PublicKey, Signature};
+use std::fmt;
+use strangemood_revival::{LicenseRevocation, Listing};
+
+/// A login request the player signs to prove they control `player`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LoginChallenge {
+    pub domain: String,
+    pub listing: Pubkey,
+    pub player: Pubkey,
+    pub nonce: [u8; 32],
+    pub issued_at: i64,
+    pub expires_at: i64,
+}
+
+impl LoginChallenge {
+    /// The exact bytes the player's wallet signs.
+    pub fn message(&self) -> Vec<u8> {
+        let nonce: String = self.nonce.iter().map(|b| format!("{:02x}", b)).collect();
+        format!(
+            "Strangemood login\n\
+             domain: {}\n\
+             listing: {}\n\
+             player: {}\n\
+             nonce: {}\n\
+             issued_at: {}\n\
+             expires_at: {}",
+            self.domain, self.listing, self.player, nonce, self.issued_at, self.expires_at
+        )
+        .into_bytes()
+    }
+}
+
+/// An account as returned by `getAccountInfo`.
+#[derive(Clone, Copy, Debug)]
+pub struct RawAccount<'a> {
+    pub address: Pubkey,
+    pub owner: Pubkey,
+    pub data: &'a [u8],
+}
+
+/// What RPC returned for a license's `[b"revocation", license]` account. Most
+/// licenses have none, but the caller has to say so for the address it queried.
+#[derive(Clone, Copy, Debug)]
+pub enum RevocationState<'a> {
+    Present(RawAccount<'a>),
+    Absent { address: Pubkey },
+}
+
+impl RevocationState<'_> {
+    pub fn address(&self) -> Pubkey {
+        match self {
+            RevocationState::Present(account) => account.address,
+            RevocationState::Absent { address } => *address,
+        }
+    }
+}
+
+/// A license that passed every check.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerifiedLicense {
+    pub player: Pubkey,
+    pub listing: Pubkey,
+    pub license: Pubkey,
+    pub quantity: u64,
+    pub expires_at: i64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerifyError {
+    InvalidSignature,
+    ChallengeExpired,
+    UnexpectedOwner(&'static str),
+    UnexpectedAddress(&'static str),
+    InvalidAccountData(&'static str),
+    UnexpectedMint,
+    UnexpectedHolder,
+    NotFrozen,
+    InsufficientQuantity,
+    Revoked,
+    Expired,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::InvalidSignature => write!(f, "signature does not match the player"),
+            VerifyError::ChallengeExpired => write!(f, "login challenge is not valid at this time"),
+            VerifyError::UnexpectedOwner(account) => {
+                write!(f, "{} has an unexpected owner", account)
+            }
+            VerifyError::UnexpectedAddress(account) => {
+                write!(f, "{} is not at its expected address", account)
+            }
+            VerifyError::InvalidAccountData(account) => {
+                write!(f, "{} could not be decoded", account)
+            }
+            VerifyError::UnexpectedMint => write!(f, "license is not for this listing"),
+            VerifyError::UnexpectedHolder => write!(f, "license is not held by the player"),
+            VerifyError::NotFrozen => write!(f, "license token account is not frozen"),
+            VerifyError::InsufficientQuantity => write!(f, "license quantity is too low"),
+            VerifyError::Revoked => write!(f, "license has been revoked"),
+            VerifyError::Expired => write!(f, "license has expired"),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/// Checks the player's signature over `challenge` at time `now`.
+pub fn verify_challenge(
+    challenge: &LoginChallenge,
+    signature: &[u8],
+    now: i64,
+) -> Result<(), VerifyError> {
+    if now < challenge.issued_at || now >= challenge.expires_at {
+        return Err(VerifyError::ChallengeExpired);
+    }
+    let public_key = PublicKey::from_bytes(challenge.player.as_ref())
+        .map_err(|_| VerifyError::InvalidSignature)?;
+    let signature = Signature::try_from(signature).map_err(|_| VerifyError::InvalidSignature)?;
+    public_key
+        .verify_strict(&challenge.message(), &signature)
+        .map_err(|_| VerifyError::InvalidSignature)
+}
+
+/// Checks that `player` holds at least `min_quantity` of a valid, frozen license
+/// for `listing`. `revocation` is what was fetched at the license's
+/// `[b"revocation", license]` address.
+pub fn verify_license(
+    player: &Pubkey,
+    min_quantity: u64,
+    now: i64,
+    listing: RawAccount,
+    mint: RawAccount,
+    license: RawAccount,
+    revocation: RevocationState,
+) -> Result<VerifiedLicense, VerifyError> {
+    let program_id = strangemood_revival::ID;
+    let token_program_id = anchor_spl::token::ID;
+
+    if listing.owner != program_id {
+        return Err(VerifyError::UnexpectedOwner("listing"));
+    }
+    if mint.owner != token_program_id {
+        return Err(VerifyError::UnexpectedOwner("mint"));
+    }
+    if license.owner != token_program_id {
+        return Err(VerifyError::UnexpectedOwner("license"));
+    }
+
+    let listing_state = Listing::try_deserialize(&mut &listing.data[..])
+        .map_err(|_| VerifyError::InvalidAccountData("listing"))?;
+    let mint_state = Mint::try_deserialize(&mut &mint.data[..])
+        .map_err(|_| VerifyError::InvalidAccountData("mint"))?;
+    let license_state = TokenAccount::try_deserialize(&mut &license.data[..])
+        .map_err(|_| VerifyError::InvalidAccountData("license"))?;
+
+    let (listing_address, _) =
+        Pubkey::find_program_address(&[b"listing", mint.address.as_ref()], &program_id);
+    if listing.address != listing_address || listing_state.mint != mint.address {
+        return Err(VerifyError::UnexpectedAddress("listing"));
+    }
+    let (mint_authority, _) =
+        Pubkey::find_program_address(&[b"mint", mint.address.as_ref()], &program_id);
+    if mint_state.freeze_authority != Some(mint_authority).into() {
+        return Err(VerifyError::UnexpectedAddress("mint"));
+    }
+
+    if license_state.mint != mint.address {
+        return Err(VerifyError::UnexpectedMint);
+    }
+    if license_state.owner != *player {
+        return Err(VerifyError::UnexpectedHolder);
+    }
+    if !license_state.is_frozen() {
+        return Err(VerifyError::NotFrozen);
+    }
+    if license_state.amount < min_quantity {
+        return Err(VerifyError::InsufficientQuantity);
+    }
+
+    let (revocation_address, _) =
+        Pubkey::find_program_address(&[b"revocation", license.address.as_ref()], &program_id);
+    if revocation.address() != revocation_address {
+        return Err(VerifyError::UnexpectedAddress("revocation"));
+    }
+
+    let mut expires_at = 0;
+    if let RevocationState::Present(revocation) = revocation {
+        if revocation.owner != program_id {
+            return Err(VerifyError::UnexpectedOwner("revocation"));
+        }
+        let record = LicenseRevocation::try_deserialize(&mut &revocation.data[..])
+            .map_err(|_| VerifyError::InvalidAccountData("revocation"))?;
+        if record.is_revoked {
+            return Err(VerifyError::Revoked);
+        }
+        if record.expires_at != 0 && now >= record.expires_at {
+            return Err(VerifyError::Expired);
+        }
+        expires_at = record.expires_at;
+    }
+
+    Ok(VerifiedLicense {
+        player: *player,
+        listing: listing.address,
+        license: license.address,
+        quantity: license_state.amount,
+        expires_at,
+    })
+}
+
+/// Verifies a signed login and the license behind it in one call.
+pub fn verify_login(
+    challenge: &LoginChallenge,
+    signature: &[u8],
+    now: i64,
+    listing: RawAccount,
+    mint: RawAccount,
+    license: RawAccount,
+    revocation: RevocationState,
+) -> Result<VerifiedLicense, VerifyError> {
+    verify_challenge(challenge, signature, now)?;
+    if listing.address != challenge.listing {
+        return Err(VerifyError::UnexpectedAddress("listing"));
+    }
+    verify_license(
+        &challenge.player,
+        1,
+        now,
+        listing,
+        mint,
+        license,
+        revocation,
+    )
+}
@@ -0,0 +1,331 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::AccountSerialize;
+use ed25519_dalek::{Keypair, PublicKey, SecretKey, Signer};
+use spl_token::solana_program::program_option::COption;
+use spl_token::solana_program::program_pack::Pack;
+use spl_token::state::{Account, AccountState, Mint};
+use strangemood_license::{
+    verify_challenge, verify_login, LoginChallenge, RawAccount, RevocationState, VerifiedLicense,
+    VerifyError,
+};
+use strangemood_revival::{LicenseRevocation, Listing};
+
+const NOW: i64 = 1_767_225_600;
+
+// The player key derived from a secret of `[7; 32]`, and its signature over the
+// fixture's challenge. Wallet integrations should reproduce both exactly.
+const PLAYER: &str = "GmaDrppBC7P5ARKV8g3djiwP89vz1jLK23V2GBjuAEGB";
+const SIGNATURE: &str = "b8bfc861bb9ffa8a6aff663dfb43b88ff38a529f047e5adb9615a3600fccb3f692a97c14e7796d80a6ab760aabe29cfd0467148c5253dd7d0266c4396ee92e00";
+
+struct Fixture {
+    player: Keypair,
+    challenge: LoginChallenge,
+    listing_address: Pubkey,
+    mint_address: Pubkey,
+    license_address: Pubkey,
+    revocation_address: Pubkey,
+    listing: Vec<u8>,
+    mint: Vec<u8>,
+    license: Vec<u8>,
+}
+
+fn player() -> Keypair {
+    let secret = SecretKey::from_bytes(&[7; 32]).unwrap();
+    let public = PublicKey::from(&secret);
+    Keypair { secret, public }
+}
+
+fn listing_data(mint: Pubkey) -> Vec<u8> {
+    let listing = Listing {
+        is_initialized: true,
+        is_available: true,
+        charter: Pubkey::new_from_array([1; 32]),
+        authority: Pubkey::new_from_array([2; 32]),
+        payment_deposit: Pubkey::new_from_array([3; 32]),
+        vote_deposit: Pubkey::new_from_array([4; 32]),
+        price: 1_000_000,
+        mint,
+        uri: "https://strangemood-revival.dev/games/super-solana-kart".to_string(),
+        is_refundable: true,
+        is_consumable: false,
+        is_campaign: false,
+        is_milestone_funded: false,
+        refund_fee_bps: 0,
+        refund_window: 0,
+        is_refund_fee_decaying: false,
+        attestation_key: Pubkey::default(),
+        pricing_key: Pubkey::default(),
+        pending_authority: Pubkey::default(),
+        has_price_table: false,
+        open_receipts: 0,
+        role_version: 0,
+        open_disputes: 0,
+    };
+    let mut data = Vec::new();
+    listing.try_serialize(&mut data).unwrap();
+    data
+}
+
+fn mint_data(mint: Pubkey) -> Vec<u8> {
+    let (authority, _) =
+        Pubkey::find_program_address(&[b"mint", mint.as_ref()], &strangemood_revival::ID);
+    let state = Mint {
+        mint_authority: COption::Some(authority),
+        supply: 1,
+        decimals: 0,
+        is_initialized: true,
+        freeze_authority: COption::Some(authority),
+    };
+    let mut data = vec![0; Mint::LEN];
+    Mint::pack(state, &mut data).unwrap();
+    data
+}
+
+fn license_data(mint: Pubkey, owner: Pubkey, amount: u64, state: AccountState) -> Vec<u8> {
+    let account = Account {
+        mint,
+        owner,
+        amount,
+        delegate: COption::None,
+        state,
+        is_native: COption::None,
+        delegated_amount: 0,
+        close_authority: COption::None,
+    };
+    let mut data = vec![0; Account::LEN];
+    Account::pack(account, &mut data).unwrap();
+    data
+}
+
+fn revocation_data(license: Pubkey, is_revoked: bool, expires_at: i64) -> Vec<u8> {
+    let revocation = LicenseRevocation {
+        is_initialized: true,
+        listing: Pubkey::default(),
+        listing_token_account: license,
+        is_revoked,
+        revoked_at: NOW - 60,
+        authority: Pubkey::new_from_array([2; 32]),
+        uri: String::new(),
+        expires_at,
+    };
+    let mut data = Vec::new();
+    revocation.try_serialize(&mut data).unwrap();
+    data
+}
+
+fn fixture() -> Fixture {
+    let player = player();
+    let player_key = Pubkey::new_from_array(player.public.to_bytes());
+    let mint_address = Pubkey::new_from_array([9; 32]);
+    let (listing_address, _) = Pubkey::find_program_address(
+        &[b"listing", mint_address.as_ref()],
+        &strangemood_revival::ID,
+    );
+    let license_address = Pubkey::new_from_array([10; 32]);
+    let (revocation_address, _) = Pubkey::find_program_address(
+        &[b"revocation", license_address.as_ref()],
+        &strangemood_revival::ID,
+    );
+
+    Fixture {
+        challenge: LoginChallenge {
+            domain: "play.example.com".to_string(),
+            listing: listing_address,
+            player: player_key,
+            nonce: [0xab; 32],
+            issued_at: NOW - 30,
+            expires_at: NOW + 270,
+        },
+        player,
+        listing_address,
+        mint_address,
+        license_address,
+        revocation_address,
+        listing: listing_data(mint_address),
+        mint: mint_data(mint_address),
+        license: license_data(mint_address, player_key, 1, AccountState::Frozen),
+    }
+}
+
+impl Fixture {
+    fn sign(&self) -> Vec<u8> {
+        self.player
+            .sign(&self.challenge.message())
+            .to_bytes()
+            .to_vec()
+    }
+
+    fn verify(
+        &self,
+        signature: &[u8],
+        revocation: Option<&[u8]>,
+    ) -> Result<VerifiedLicense, VerifyError> {
+        let revocation = match revocation {
+            Some(data) => RevocationState::Present(RawAccount {
+                address: self.revocation_address,
+                owner: strangemood_revival::ID,
+                data,
+            }),
+            None => RevocationState::Absent {
+                address: self.revocation_address,
+            },
+        };
+        self.verify_with(signature, revocation)
+    }
+
+    fn verify_with(
+        &self,
+        signature: &[u8],
+        revocation: RevocationState,
+    ) -> Result<VerifiedLicense, VerifyError> {
+        verify_login(
+            &self.challenge,
+            signature,
+            NOW,
+            RawAccount {
+                address: self.listing_address,
+                owner: strangemood_revival::ID,
+                data: &self.listing,
+            },
+            RawAccount {
+                address: self.mint_address,
+                owner: spl_token::ID,
+                data: &self.mint,
+            },
+            RawAccount {
+                address: self.license_address,
+                owner: spl_token::ID,
+                data: &self.license,
+            },
+            revocation,
+        )
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[test]
+fn challenge_message_vector() {
+    let f = fixture();
+    let expected = format!(
+        "Strangemood login\n\
+         domain: play.example.com\n\
+         listing: {}\n\
+         player: {}\n\
+         nonce: {}\n\
+         issued_at: 1767225570\n\
+         expires_at: 1767225870",
+        f.listing_address,
+        f.challenge.player,
+        "ab".repeat(32)
+    );
+    assert_eq!(String::from_utf8(f.challenge.message()).unwrap(), expected);
+}
+
+#[test]
+fn signature_vector() {
+    let f = fixture();
+    assert_eq!(f.challenge.player.to_string(), PLAYER);
+    assert_eq!(hex(&f.sign()), SIGNATURE);
+    assert_eq!(verify_challenge(&f.challenge, &f.sign(), NOW), Ok(()));
+}
+
+#[test]
+fn verifies_a_frozen_license() {
+    let f = fixture();
+    let license = f.verify(&f.sign(), None).unwrap();
+    assert_eq!(license.player, f.challenge.player);
+    assert_eq!(license.listing, f.listing_address);
+    assert_eq!(license.license, f.license_address);
+    assert_eq!(license.quantity, 1);
+    assert_eq!(license.expires_at, 0);
+}
+
+#[test]
+fn rejects_a_forged_signature() {
+    let f = fixture();
+    let mut signature = f.sign();
+    signature[0] ^= 1;
+    assert_eq!(
+        f.verify(&signature, None),
+        Err(VerifyError::InvalidSignature)
+    );
+}
+
+#[test]
+fn rejects_an_expired_challenge() {
+    let f = fixture();
+    assert_eq!(
+        verify_challenge(&f.challenge, &f.sign(), f.challenge.expires_at),
+        Err(VerifyError::ChallengeExpired)
+    );
+}
+
+#[test]
+fn rejects_a_thawed_license() {
+    let mut f = fixture();
+    f.license = license_data(
+        f.mint_address,
+        f.challenge.player,
+        1,
+        AccountState::Initialized,
+    );
+    assert_eq!(f.verify(&f.sign(), None), Err(VerifyError::NotFrozen));
+}
+
+#[test]
+fn rejects_someone_elses_license() {
+    let mut f = fixture();
+    f.license = license_data(
+        f.mint_address,
+        Pubkey::new_from_array([11; 32]),
+        1,
+        AccountState::Frozen,
+    );
+    assert_eq!(
+        f.verify(&f.sign(), None),
+        Err(VerifyError::UnexpectedHolder)
+    );
+}
+
+#[test]
+fn rejects_a_revoked_license() {
+    let f = fixture();
+    let revocation = revocation_data(f.license_address, true, 0);
+    assert_eq!(
+        f.verify(&f.sign(), Some(&revocation)),
+        Err(VerifyError::Revoked)
+    );
+}
+
+#[test]
+fn rejects_an_expired_license() {
+    let f = fixture();
+    let revocation = revocation_data(f.license_address, false, NOW);
+    assert_eq!(
+        f.verify(&f.sign(), Some(&revocation)),
+        Err(VerifyError::Expired)
+    );
+
+    let reinstated = revocation_data(f.license_address, false, NOW + 3600);
+    assert_eq!(
+        f.verify(&f.sign(), Some(&reinstated)).unwrap().expires_at,
+        NOW + 3600
+    );
+}
+
+#[test]
+fn rejects_an_absent_revocation_at_the_wrong_address() {
+    let f = fixture();
+    assert_eq!(
+        f.verify_with(
+            &f.sign(),
+            RevocationState::Absent {
+                address: Pubkey::new_from_array([12; 32]),
+            },
+        ),
+        Err(VerifyError::UnexpectedAddress("revocation"))
+    );
+}
@@ -1,4 +1,7 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::{hash, hashv};
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke_signed;
 use anchor_lang::solana_program::system_instruction;
 use anchor_spl::token::{Mint, Token, TokenAccount};
 
@@ -23,6 +26,7 @@ pub fn mint_to_and_freeze<'a>(
     freeze_account(token_program, mint, to, authority, bump)
 }
 
+#[allow(clippy::clone_on_copy)]
 pub fn mint_to<'a>(
     token_program: AccountInfo<'a>,
     mint: AccountInfo<'a>,
@@ -44,6 +48,7 @@ pub fn mint_to<'a>(
     anchor_spl::token::mint_to(cpi_ctx, amount)
 }
 
+#[allow(clippy::clone_on_copy)]
 pub fn freeze_account<'a>(
     token_program: AccountInfo<'a>,
     mint: AccountInfo<'a>,
@@ -64,6 +69,28 @@ pub fn freeze_account<'a>(
     anchor_spl::token::freeze_account(cpi_ctx)
 }
 
+#[allow(clippy::clone_on_copy)]
+pub fn thaw_account<'a>(
+    token_program: AccountInfo<'a>,
+    mint: AccountInfo<'a>,
+    account: AccountInfo<'a>,
+    authority: AccountInfo<'a>,
+    bump: u8,
+) -> Result<()> {
+    let cpi_program = token_program;
+    let cloned_mint = mint.key.clone();
+    let cpi_accounts = anchor_spl::token::ThawAccount {
+        mint,
+        account,
+        authority,
+    };
+    let seeds = &[b"mint", cloned_mint.as_ref(), &[bump]];
+    let signers = &[&seeds[..]];
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signers);
+    anchor_spl::token::thaw_account(cpi_ctx)
+}
+
+#[allow(clippy::clone_on_copy)]
 pub fn token_escrow_transfer<'a>(
     token_program: AccountInfo<'a>,
     from: AccountInfo<'a>,
@@ -85,6 +112,25 @@ pub fn token_escrow_transfer<'a>(
     anchor_spl::token::transfer(cpi_ctx, amount)
 }
 
+pub fn token_signed_transfer<'a>(
+    token_program: AccountInfo<'a>,
+    from: AccountInfo<'a>,
+    to: AccountInfo<'a>,
+    authority: AccountInfo<'a>,
+    amount: u64,
+    seeds: &[&[u8]],
+) -> Result<()> {
+    let cpi_program = token_program;
+    let cpi_accounts = anchor_spl::token::Transfer {
+        from,
+        to,
+        authority,
+    };
+    let signers = &[seeds];
+    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signers);
+    anchor_spl::token::transfer(cpi_ctx, amount)
+}
+
 pub fn token_transfer<'a>(
     token_program: AccountInfo<'a>,
     from: AccountInfo<'a>,
@@ -102,6 +148,7 @@ pub fn token_transfer<'a>(
     anchor_spl::token::transfer(cpi_ctx, amount)
 }
 
+#[allow(clippy::clone_on_copy)]
 pub fn burn<'a>(
     token_program: AccountInfo<'a>,
     mint: AccountInfo<'a>,
@@ -123,6 +170,64 @@ pub fn burn<'a>(
     anchor_spl::token::burn(cpi_ctx, amount)
 }
 
+/// Burns licenses from an account `mint_to_and_freeze` may have frozen,
+/// freezing whatever is left again.
+pub fn thaw_and_burn<'a>(
+    token_program: AccountInfo<'a>,
+    mint: AccountInfo<'a>,
+    account: &Account<'a, TokenAccount>,
+    authority: AccountInfo<'a>,
+    bump: u8,
+    amount: u64,
+) -> Result<()> {
+    let is_frozen = account.is_frozen();
+    if is_frozen {
+        thaw_account(
+            token_program.clone(),
+            mint.clone(),
+            account.to_account_info(),
+            authority.clone(),
+            bump,
+        )?;
+    }
+    burn(
+        token_program.clone(),
+        mint.clone(),
+        account.to_account_info(),
+        authority.clone(),
+        bump,
+        amount,
+    )?;
+    if is_frozen && account.amount > amount {
+        freeze_account(
+            token_program,
+            mint,
+            account.to_account_info(),
+            authority,
+            bump,
+        )?;
+    }
+    Ok(())
+}
+
+pub fn holder_burn<'a>(
+    token_program: AccountInfo<'a>,
+    mint: AccountInfo<'a>,
+    account: AccountInfo<'a>,
+    authority: AccountInfo<'a>,
+    amount: u64,
+) -> Result<()> {
+    let cpi_program = token_program;
+    let cpi_accounts = anchor_spl::token::Burn {
+        mint,
+        from: account,
+        authority,
+    };
+    let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+    anchor_spl::token::burn(cpi_ctx, amount)
+}
+
+#[allow(clippy::cloned_ref_to_slice_refs)]
 pub fn sync_native<'a>(token_program: &AccountInfo<'a>, account: AccountInfo<'a>) -> Result<()> {
     let ix = spl_token::instruction::sync_native(&token_program.key(), &account.key())?;
     anchor_lang::solana_program::program::invoke(&ix, &[account.clone()])?;
@@ -143,6 +248,75 @@ pub fn system_transfer<'a>(
     Ok(())
 }
 
+/// Checks that the instruction just before this one is an ed25519 signature-verify
+/// precompile instruction over `message`, signed by `signer`.
+pub fn verify_ed25519_instruction(
+    instructions: &AccountInfo,
+    signer: &Pubkey,
+    message: &[u8],
+) -> Result<()> {
+    use anchor_lang::solana_program::{ed25519_program, sysvar::instructions};
+
+    let current = instructions::load_current_index_checked(instructions)?;
+    if current == 0 {
+        return Err(StrangemoodError::MissingSignatureVerification.into());
+    }
+    let ix = instructions::load_instruction_at_checked(current as usize - 1, instructions)?;
+    if ix.program_id != ed25519_program::ID {
+        return Err(StrangemoodError::MissingSignatureVerification.into());
+    }
+
+    // One signature, with its offsets pointing into this same instruction.
+    let data = &ix.data;
+    if data.len() < 16 || data[0] != 1 {
+        return Err(StrangemoodError::InvalidSignatureVerification.into());
+    }
+    let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+    let public_key_offset = read_u16(6) as usize;
+    let message_offset = read_u16(10) as usize;
+    let message_size = read_u16(12) as usize;
+    if read_u16(4) != u16::MAX || read_u16(8) != u16::MAX || read_u16(14) != u16::MAX {
+        return Err(StrangemoodError::InvalidSignatureVerification.into());
+    }
+
+    let public_key = data.get(public_key_offset..public_key_offset + 32);
+    let signed = data.get(message_offset..message_offset + message_size);
+    if public_key != Some(signer.as_ref()) || signed != Some(message) {
+        return Err(StrangemoodError::InvalidSignatureVerification.into());
+    }
+    Ok(())
+}
+
+/// The message a game server signs to attest that `player` consumed `amount`.
+pub fn consume_attestation_message(
+    listing: &Pubkey,
+    player: &Pubkey,
+    amount: u64,
+    nonce: u64,
+) -> Vec<u8> {
+    [
+        b"strangemood:consume".as_ref(),
+        listing.as_ref(),
+        player.as_ref(),
+        &amount.to_le_bytes(),
+        &nonce.to_le_bytes(),
+    ]
+    .concat()
+}
+
+/// Checks a Merkle proof built with sorted-pair sha256 hashing.
+pub fn verify_merkle_proof(proof: &[[u8; 32]], root: &[u8; 32], leaf: [u8; 32]) -> bool {
+    let mut node = leaf;
+    for sibling in proof {
+        node = if node <= *sibling {
+            hashv(&[&node, sibling]).to_bytes()
+        } else {
+            hashv(&[sibling, &node]).to_bytes()
+        };
+    }
+    node == *root
+}
+
 pub fn erase_data<'a>(account: &AccountInfo<'a>) {
     let mut data = account.data.borrow_mut();
     data.fill(0);
@@ -175,6 +349,7 @@ pub fn close_native_account<'a>(
     erase_data(source_account_info);
 }
 
+#[allow(clippy::clone_on_copy)]
 pub fn close_token_escrow_account<'a>(
     token_program: AccountInfo<'a>,
     from: AccountInfo<'a>,
@@ -199,6 +374,7 @@ pub fn close_token_escrow_account<'a>(
 pub mod strangemood_revival {
     use super::*;
 
+    #[allow(clippy::too_many_arguments)]
     pub fn init_listing(
         ctx: Context<InitListing>,
         _mint_bump: u8,
@@ -238,6 +414,7 @@ pub mod strangemood_revival {
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn purchase(
         ctx: Context<Purchase>,
         receipt_nonce: u128,
@@ -245,6 +422,8 @@ pub mod strangemood_revival {
         listing_mint_bump: u8,
         _escrow_authority_bump: u8,
         amount: u64,
+        max_unit_price: u64,
+        voucher: Option<PurchaseVoucher>,
     ) -> Result<()> {
         msg!("Purchasing");
         let listing = ctx.accounts.listing.clone().into_inner();
@@ -256,12 +435,87 @@ pub mod strangemood_revival {
             return Err(StrangemoodError::UnexpectedListingMint.into());
         }
 
+        let terms = listing.payment_terms(
+            ctx.accounts.price_table.as_deref().map(|t| &**t),
+            ctx.accounts.listing_payment_deposit.key(),
+            ctx.accounts.listing_payment_deposit_mint.key(),
+        )?;
+        // Without a voucher the seed would default to 0 and burn the pricing
+        // key's first nonce.
+        if voucher.is_none() && ctx.accounts.voucher_nonce.is_some() {
+            return Err(StrangemoodError::UnexpectedNonceAccount.into());
+        }
+        let price = match (voucher, terms.oracle) {
+            (Some(voucher), _) => {
+                if listing.pricing_key == Pubkey::default() {
+                    return Err(StrangemoodError::UnauthorizedAuthority.into());
+                }
+                if Clock::get()?.unix_timestamp >= voucher.expires_at {
+                    return Err(StrangemoodError::VoucherExpired.into());
+                }
+                let instructions = match (&ctx.accounts.voucher_nonce, &ctx.accounts.instructions) {
+                    (Some(_), Some(instructions)) => instructions,
+                    _ => return Err(StrangemoodError::MissingSignatureVerification.into()),
+                };
+                verify_ed25519_instruction(
+                    instructions,
+                    &listing.pricing_key,
+                    &voucher.message(
+                        &ctx.accounts.listing.key(),
+                        &ctx.accounts.user.key(),
+                        &ctx.accounts.listing_payment_deposit_mint.key(),
+                    ),
+                )?;
+                voucher.price
+            }
+            (None, None) => terms.price,
+            (None, Some(oracle)) => {
+                let feed = match &ctx.accounts.price_feed {
+                    Some(feed) if feed.key() == oracle.price_feed => feed,
+                    _ => return Err(StrangemoodError::UnexpectedPriceFeed.into()),
+                };
+                let quote = oracle.kind.load(feed)?;
+                quote.check(&oracle, Clock::get()?.unix_timestamp)?;
+                quote.convert_usd(
+                    terms.price,
+                    ctx.accounts.listing_payment_deposit_mint.decimals,
+                )?
+            }
+        };
+        if price > max_unit_price {
+            return Err(StrangemoodError::SlippageExceeded.into());
+        }
+        if listing.is_milestone_funded
+            && ctx.accounts.listing_payment_deposit.key() != listing.payment_deposit
+        {
+            return Err(StrangemoodError::DepositIsNotFoundInListing.into());
+        }
+
+        if listing.is_campaign {
+            let campaign = match ctx.accounts.campaign.as_deref_mut() {
+                Some(campaign) => campaign,
+                None => return Err(StrangemoodError::CampaignRequired.into()),
+            };
+            // A funded campaign keeps selling past its deadline.
+            if !campaign.is_funded && Clock::get()?.unix_timestamp >= campaign.deadline {
+                return Err(StrangemoodError::CampaignEnded.into());
+            }
+            if campaign.mint != ctx.accounts.listing_payment_deposit_mint.key() {
+                return Err(StrangemoodError::MintNotSupported.into());
+            }
+            campaign.raised += amount * price;
+            campaign.backers += 1;
+            if campaign.raised >= campaign.goal {
+                campaign.is_funded = true;
+            }
+        }
+
         token_transfer(
             ctx.accounts.token_program.to_account_info(),
             ctx.accounts.purchase_token_account.to_account_info(),
             ctx.accounts.escrow.to_account_info(),
             ctx.accounts.user.to_account_info(),
-            amount * listing.price,
+            amount * price,
         )?;
 
         msg!("Transferred tokens");
@@ -286,9 +540,11 @@ pub mod strangemood_revival {
         receipt.listing_token_account = ctx.accounts.listing_token_account.key();
         receipt.cashier = ctx.accounts.cashier.key();
         receipt.nonce = receipt_nonce;
-        receipt.price = listing.price;
+        receipt.price = price;
+        receipt.purchased_at = Clock::get()?.unix_timestamp;
         receipt.escrow = ctx.accounts.escrow.key();
-        receipt.is_cashable = !listing.is_refundable;
+        receipt.is_cashable = !listing.is_refundable && !listing.is_campaign;
+        ctx.accounts.listing.open_receipts += 1;
 
         Ok(())
     }
@@ -303,10 +559,27 @@ pub mod strangemood_revival {
         let charter = ctx.accounts.charter.clone().into_inner();
         let receipt = ctx.accounts.receipt.clone().into_inner();
 
-        if !receipt.is_cashable {
+        let is_funded = match (listing.is_campaign, &ctx.accounts.campaign) {
+            (false, _) => false,
+            (true, Some(campaign)) => campaign.is_funded,
+            (true, None) => return Err(StrangemoodError::CampaignRequired.into()),
+        };
+        if !receipt.is_cashable && !is_funded {
             return Err(StrangemoodError::ReceiptNotCashable.into());
         }
-        if receipt.cashier != ctx.accounts.cashier.key() {
+        if receipt.is_disputed {
+            return Err(StrangemoodError::ReceiptDisputed.into());
+        }
+        let cashier = ctx.accounts.cashier.key();
+        if receipt.cashier != cashier
+            && !ListingRole::grants(
+                ctx.accounts.role.as_deref().map(|r| &**r),
+                &receipt.listing,
+                listing.role_version,
+                &cashier,
+                ROLE_CASHIER,
+            )
+        {
             return Err(StrangemoodError::OnlyCashableByTheCashier.into());
         }
         if listing.mint != ctx.accounts.listing_mint.key() {
@@ -315,10 +588,22 @@ pub mod strangemood_revival {
         if ctx.accounts.listing_token_account.key() != receipt.listing_token_account {
             return Err(StrangemoodError::UnexpectedListingTokenAccount.into());
         }
-        if listing.vote_deposit != ctx.accounts.listings_vote_deposit.key()
-            || listing.payment_deposit != ctx.accounts.listings_payment_deposit.key()
-        {
-            return Err(StrangemoodError::DepositIsNotFoundInListing.into());
+        listing.payment_terms(
+            ctx.accounts.price_table.as_deref().map(|t| &**t),
+            ctx.accounts.listings_payment_deposit.key(),
+            ctx.accounts.escrow.mint,
+        )?;
+        if listing.is_milestone_funded {
+            let milestones = match &ctx.accounts.milestones {
+                Some(milestones) => milestones,
+                None => return Err(StrangemoodError::MilestonesRequired.into()),
+            };
+            if milestones.is_abandoned {
+                return Err(StrangemoodError::MilestonesAbandoned.into());
+            }
+            if milestones.vault != ctx.accounts.listings_payment_deposit.key() {
+                return Err(StrangemoodError::DepositIsNotFoundInListing.into());
+            }
         }
         if listing.charter != ctx.accounts.charter.key() {
             return Err(StrangemoodError::UnauthorizedCharter.into());
@@ -341,7 +626,7 @@ pub mod strangemood_revival {
             )?;
         }
 
-        let lamports: u64 = receipt.price;
+        let lamports: u64 = ctx.accounts.escrow.amount;
         let deposit_rate = 1.0 - charter.payment_contribution_rate();
         let deposit_amount = (deposit_rate * lamports as f64) as u64;
         let contribution_amount = lamports - deposit_amount;
@@ -355,6 +640,23 @@ pub mod strangemood_revival {
             escrow_authority_bump,
         )?;
 
+        if let Some(milestones) = ctx.accounts.milestones.as_deref_mut() {
+            milestones.deposited += deposit_amount;
+            milestones.licenses += receipt.quantity;
+
+            let backing = ctx
+                .accounts
+                .backing
+                .as_deref_mut()
+                .ok_or(StrangemoodError::MilestoneBackingRequired)?;
+            if !backing.is_initialized {
+                backing.is_initialized = true;
+                backing.milestones = milestones.key();
+                backing.purchaser = receipt.purchaser;
+            }
+            backing.licenses += receipt.quantity;
+        }
+
         token_escrow_transfer(
             ctx.accounts.token_program.to_account_info(),
             ctx.accounts.escrow.to_account_info(),
@@ -370,24 +672,81 @@ pub mod strangemood_revival {
             contribution_amount,
         );
 
+        let now = Clock::get()?.unix_timestamp;
+        let epoch = charter.emission_epoch(now);
         let treasury = ctx.accounts.charter_treasury.clone().into_inner();
         let votes = contribution_amount as f64
             * charter.expansion_rate(
                 treasury.expansion_scalar_amount,
                 treasury.expansion_scalar_decimals,
-            );
+            )
+            * charter.emission_factor(epoch);
+
+        // Past the charter's emission caps, payments still settle but mint less.
+        let emissions = &mut ctx.accounts.emissions;
+        if !emissions.is_initialized {
+            emissions.is_initialized = true;
+            emissions.charter = ctx.accounts.charter.key();
+        }
+        let votes = emissions.record(&charter, epoch, votes as u64);
+
+        // The buyer's share comes off the top; the rest splits between the
+        // listing and the charter as before. The listing's own authority and
+        // role holders don't earn it on their own sales.
+        let is_insider = receipt.purchaser == listing.authority
+            || ListingRole::is_held(
+                &ctx.accounts.purchaser_role,
+                &receipt.listing,
+                listing.role_version,
+            )?;
+        let buyer_amount = if is_insider {
+            0
+        } else {
+            (charter.buyer_vote_rate() * votes as f64) as u64
+        };
+        let votes = votes - buyer_amount;
+        if buyer_amount > 0 {
+            let purchaser_vote_deposit = ctx
+                .accounts
+                .purchaser_vote_deposit
+                .as_ref()
+                .ok_or(StrangemoodError::PurchaserVoteDepositRequired)?;
+            mint_to(
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.charter_mint.to_account_info(),
+                purchaser_vote_deposit.to_account_info(),
+                ctx.accounts.charter_mint_authority.to_account_info(),
+                charter_mint_bump,
+                buyer_amount,
+            )?;
+        }
+
         let deposit_rate = 1.0 - charter.vote_contribution_rate();
         let deposit_amount = (deposit_rate * votes as f64) as u64;
-        let contribution_amount = (votes as u64) - deposit_amount;
+        let contribution_amount = votes - deposit_amount;
 
+        // The listing's share vests before it reaches the listing's vote deposit.
         mint_to(
             ctx.accounts.token_program.to_account_info(),
             ctx.accounts.charter_mint.to_account_info(),
-            ctx.accounts.listings_vote_deposit.to_account_info(),
+            ctx.accounts.vesting_vault.to_account_info(),
             ctx.accounts.charter_mint_authority.to_account_info(),
             charter_mint_bump,
             deposit_amount,
         )?;
+        let vesting = &mut ctx.accounts.vesting;
+        if !vesting.is_initialized {
+            vesting.is_initialized = true;
+            vesting.listing = receipt.listing;
+            vesting.vault = ctx.accounts.vesting_vault.key();
+            vesting.mint = charter.mint;
+        }
+        vesting.deposit(
+            deposit_amount,
+            now,
+            charter.vote_vesting_cliff,
+            charter.vote_vesting_duration,
+        );
 
         mint_to(
             ctx.accounts.token_program.to_account_info(),
@@ -406,6 +765,7 @@ pub mod strangemood_revival {
             escrow_authority_bump,
         )?;
 
+        ctx.accounts.listing.open_receipts -= 1;
         close_native_account(
             &ctx.accounts.receipt.to_account_info(),
             &ctx.accounts.cashier.to_account_info(),
@@ -419,20 +779,182 @@ pub mod strangemood_revival {
         _listing_bump: u8,
         listing_mint_bump: u8,
         escrow_authority_bump: u8,
+        quantity: u64,
     ) -> Result<()> {
+        let listing = ctx.accounts.listing.clone().into_inner();
         let receipt = ctx.accounts.receipt.clone().into_inner();
 
+        if quantity == 0 || quantity > receipt.quantity {
+            return Err(StrangemoodError::InvalidQuantity.into());
+        }
+        if receipt.is_disputed {
+            return Err(StrangemoodError::ReceiptDisputed.into());
+        }
+        let is_closing = quantity == receipt.quantity;
+        let amount = if is_closing {
+            ctx.accounts.escrow.amount
+        } else {
+            quantity * receipt.price
+        };
+
+        let now = Clock::get()?.unix_timestamp;
+        let mut has_failed = false;
+        if listing.is_campaign {
+            let campaign = match ctx.accounts.campaign.as_deref_mut() {
+                Some(campaign) => campaign,
+                None => return Err(StrangemoodError::CampaignRequired.into()),
+            };
+            if campaign.is_funded {
+                return Err(StrangemoodError::CampaignFunded.into());
+            }
+            has_failed = now >= campaign.deadline;
+            if !has_failed && !receipt.is_refundable {
+                return Err(StrangemoodError::CampaignInProgress.into());
+            }
+            campaign.withdraw(amount, is_closing)?;
+        }
+
+        // Backers of a failed campaign always get everything back.
+        let fee = if has_failed {
+            0
+        } else {
+            listing.refund_fee(amount, receipt.purchased_at, now)?
+        };
+
         if receipt.is_refundable {
-            burn(
+            thaw_and_burn(
                 ctx.accounts.token_program.to_account_info(),
                 ctx.accounts.listing_mint.to_account_info(),
-                ctx.accounts.listing_token_account.to_account_info(),
+                &ctx.accounts.listing_token_account,
+                ctx.accounts.listing_mint_authority.to_account_info(),
+                listing_mint_bump,
+                quantity,
+            )?;
+        }
+
+        if fee > 0 {
+            let (payment_deposit, charter, treasury, treasury_deposit) = match (
+                &ctx.accounts.listings_payment_deposit,
+                &ctx.accounts.charter,
+                &ctx.accounts.charter_treasury,
+                &ctx.accounts.charter_treasury_deposit,
+            ) {
+                (Some(a), Some(b), Some(c), Some(d)) => (a, b, c, d),
+                _ => return Err(StrangemoodError::RefundFeeAccountsRequired.into()),
+            };
+            if listing.charter != charter.key() || treasury.charter != charter.key() {
+                return Err(StrangemoodError::UnauthorizedCharter.into());
+            }
+            if treasury.deposit != treasury_deposit.key()
+                || treasury.mint != ctx.accounts.escrow.mint
+            {
+                return Err(StrangemoodError::DepositIsNotFoundInCharter.into());
+            }
+            listing.payment_terms(
+                ctx.accounts.price_table.as_deref().map(|t| &**t),
+                payment_deposit.key(),
+                ctx.accounts.escrow.mint,
+            )?;
+
+            let deposit_rate = 1.0 - charter.payment_contribution_rate();
+            let deposit_amount = (deposit_rate * fee as f64) as u64;
+            let contribution_amount = fee - deposit_amount;
+
+            token_escrow_transfer(
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.escrow.to_account_info(),
+                payment_deposit.to_account_info(),
+                ctx.accounts.escrow_authority.to_account_info(),
+                deposit_amount,
+                escrow_authority_bump,
+            )?;
+
+            token_escrow_transfer(
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.escrow.to_account_info(),
+                treasury_deposit.to_account_info(),
+                ctx.accounts.escrow_authority.to_account_info(),
+                contribution_amount,
+                escrow_authority_bump,
+            )?;
+        }
+
+        token_escrow_transfer(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.escrow.to_account_info(),
+            ctx.accounts.return_deposit.to_account_info(),
+            ctx.accounts.escrow_authority.to_account_info(),
+            amount - fee,
+            escrow_authority_bump,
+        )?;
+
+        if !is_closing {
+            ctx.accounts.receipt.quantity -= quantity;
+            return Ok(());
+        }
+
+        ctx.accounts.listing.open_receipts -= 1;
+        close_native_account(
+            &ctx.accounts.receipt.to_account_info(),
+            &ctx.accounts.purchaser.to_account_info(),
+        );
+
+        close_token_escrow_account(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.escrow.to_account_info(),
+            ctx.accounts.purchaser.to_account_info(),
+            ctx.accounts.escrow_authority.to_account_info(),
+            escrow_authority_bump,
+        )?;
+
+        Ok(())
+    }
+
+    pub fn refund(
+        ctx: Context<Refund>,
+        listing_mint_bump: u8,
+        escrow_authority_bump: u8,
+    ) -> Result<()> {
+        let authority = ctx.accounts.authority.key();
+        let arbiter = ctx.accounts.charter.arbiter;
+        if authority != ctx.accounts.listing.authority
+            && (arbiter == Pubkey::default() || authority != arbiter)
+            && !ListingRole::grants(
+                ctx.accounts.role.as_deref().map(|r| &**r),
+                &ctx.accounts.listing.key(),
+                ctx.accounts.listing.role_version,
+                &authority,
+                ROLE_SUPPORT,
+            )
+        {
+            return Err(StrangemoodError::UnauthorizedAuthority.into());
+        }
+
+        let receipt = ctx.accounts.receipt.clone().into_inner();
+        if receipt.is_disputed {
+            return Err(StrangemoodError::ReceiptDisputed.into());
+        }
+
+        if ctx.accounts.listing.is_campaign {
+            let campaign = match ctx.accounts.campaign.as_deref_mut() {
+                Some(campaign) => campaign,
+                None => return Err(StrangemoodError::CampaignRequired.into()),
+            };
+            campaign.withdraw(ctx.accounts.escrow.amount, true)?;
+        }
+
+        if receipt.is_refundable {
+            thaw_and_burn(
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.listing_mint.to_account_info(),
+                &ctx.accounts.listing_token_account,
                 ctx.accounts.listing_mint_authority.to_account_info(),
                 listing_mint_bump,
                 receipt.quantity,
             )?;
         }
 
+        ctx.accounts.listing.open_receipts -= 1;
         close_native_account(
             &ctx.accounts.receipt.to_account_info(),
             &ctx.accounts.purchaser.to_account_info(),
@@ -458,653 +980,4477 @@ pub mod strangemood_revival {
         Ok(())
     }
 
+    pub fn open_dispute(ctx: Context<OpenDispute>, _dispute_bump: u8, uri: String) -> Result<()> {
+        let charter = &ctx.accounts.charter;
+        if charter.arbiter == Pubkey::default() {
+            return Err(StrangemoodError::ArbiterNotSet.into());
+        }
+        if ctx.accounts.receipt.is_disputed {
+            return Err(StrangemoodError::ReceiptDisputed.into());
+        }
+        // A release ruling is final; the purchaser can't reopen it.
+        if ctx.accounts.receipt.was_disputed {
+            return Err(StrangemoodError::ReceiptAlreadyDisputed.into());
+        }
+
+        let opened_at = Clock::get()?.unix_timestamp;
+        let dispute = &mut ctx.accounts.dispute;
+        dispute.is_initialized = true;
+        dispute.receipt = ctx.accounts.receipt.key();
+        dispute.charter = charter.key();
+        dispute.purchaser = ctx.accounts.purchaser.key();
+        dispute.opened_at = opened_at;
+        dispute.deadline = opened_at + charter.dispute_period;
+        dispute.uri = uri;
+
+        ctx.accounts.receipt.is_disputed = true;
+        ctx.accounts.receipt.was_disputed = true;
+        ctx.accounts.listing.open_disputes += 1;
+
+        Ok(())
+    }
+
+    pub fn resolve_dispute(
+        ctx: Context<ResolveDispute>,
+        listing_mint_bump: u8,
+        escrow_authority_bump: u8,
+        ruling: DisputeRuling,
+    ) -> Result<()> {
+        if ctx.accounts.authority.key() != ctx.accounts.charter.arbiter {
+            return Err(StrangemoodError::UnauthorizedAuthority.into());
+        }
+        ctx.accounts
+            .execute(ruling, listing_mint_bump, escrow_authority_bump)
+    }
+
+    pub fn settle_dispute(
+        ctx: Context<ResolveDispute>,
+        listing_mint_bump: u8,
+        escrow_authority_bump: u8,
+    ) -> Result<()> {
+        if Clock::get()?.unix_timestamp < ctx.accounts.dispute.deadline {
+            return Err(StrangemoodError::DisputeInProgress.into());
+        }
+        let ruling = if ctx.accounts.charter.is_dispute_default_refund {
+            DisputeRuling::Refund
+        } else {
+            DisputeRuling::Release
+        };
+        ctx.accounts
+            .execute(ruling, listing_mint_bump, escrow_authority_bump)
+    }
+
     pub fn consume(
         ctx: Context<Consume>,
         _receipt_bump: u8,
         listing_mint_bump: u8,
         amount: u64,
+        attestation_nonce: Option<u64>,
     ) -> Result<()> {
         let listing = ctx.accounts.listing.clone().into_inner();
 
-        if ctx.accounts.authority.key() != listing.authority {
-            return Err(StrangemoodError::UnauthorizedAuthority.into());
+        // The holder signs, a session key signs for them, or the game server
+        // attests. Consumer role holders can only attest, like a game server.
+        let authority = ctx.accounts.authority.key();
+        let holder = ctx.accounts.listing_token_account.owner;
+        if let Some(nonce) = attestation_nonce {
+            let attester = match ctx.accounts.role.as_deref() {
+                Some(role)
+                    if ListingRole::grants(
+                        Some(role),
+                        &ctx.accounts.listing.key(),
+                        listing.role_version,
+                        &role.grantee,
+                        ROLE_CONSUMER,
+                    ) =>
+                {
+                    role.grantee
+                }
+                _ => listing.attestation_key,
+            };
+            if attester == Pubkey::default() {
+                return Err(StrangemoodError::UnauthorizedAuthority.into());
+            }
+            let instructions = match (&ctx.accounts.consumed_nonce, &ctx.accounts.instructions) {
+                (Some(_), Some(instructions)) => instructions,
+                _ => return Err(StrangemoodError::MissingSignatureVerification.into()),
+            };
+            verify_ed25519_instruction(
+                instructions,
+                &attester,
+                &consume_attestation_message(&ctx.accounts.listing.key(), &holder, amount, nonce),
+            )?;
+        } else if ctx.accounts.consumed_nonce.is_some() {
+            // Without a nonce the seed would default to 0 and burn the
+            // server's first attestation.
+            return Err(StrangemoodError::UnexpectedNonceAccount.into());
+        } else if authority != holder {
+            let session = match ctx.accounts.session.as_deref_mut() {
+                Some(session) if session.session_key == authority => session,
+                _ => return Err(StrangemoodError::UnauthorizedAuthority.into()),
+            };
+            if session.listing_token_account != ctx.accounts.listing_token_account.key() {
+                return Err(StrangemoodError::UnexpectedListingTokenAccount.into());
+            }
+            session.authorize(SESSION_SCOPE_CONSUME, amount, Clock::get()?.unix_timestamp)?;
         }
 
         if !listing.is_consumable {
             return Err(StrangemoodError::ListingIsNotConsumable.into());
         }
 
-        burn(
+        thaw_and_burn(
             ctx.accounts.token_program.to_account_info(),
             ctx.accounts.mint.to_account_info(),
-            ctx.accounts.listing_token_account.to_account_info(),
+            &ctx.accounts.listing_token_account,
             ctx.accounts.mint_authority.to_account_info(),
             listing_mint_bump,
             amount,
-        )?;
-
-        Ok(())
+        )
     }
 
-    pub fn set_receipt_cashable(ctx: Context<SetReceiptCashable>) -> Result<()> {
-        if ctx.accounts.authority.key() != ctx.accounts.listing.authority.key() {
-            return Err(StrangemoodError::UnauthorizedAuthority.into());
+    pub fn create_session(
+        ctx: Context<CreateSession>,
+        _session_bump: u8,
+        scope: u8,
+        max_units: u64,
+        expires_at: i64,
+    ) -> Result<()> {
+        if expires_at <= Clock::get()?.unix_timestamp {
+            return Err(StrangemoodError::SessionExpired.into());
         }
 
-        let receipt = &mut ctx.accounts.receipt;
-        receipt.is_cashable = true;
+        let session = &mut ctx.accounts.session;
+        session.is_initialized = true;
+        session.holder = ctx.accounts.holder.key();
+        session.listing_token_account = ctx.accounts.listing_token_account.key();
+        session.session_key = ctx.accounts.session_key.key();
+        session.scope = scope;
+        session.max_units = max_units;
+        session.used_units = 0;
+        session.expires_at = expires_at;
 
         Ok(())
     }
 
-    pub fn init_charter(
-        ctx: Context<InitCharter>,
-        _charter_bump: u8,
-        expansion_rate_amount: u64,
-        expansion_rate_decimals: u8,
-        sol_contribution_rate_amount: u64,
-        sol_contribution_rate_decimals: u8,
-        vote_contribution_rate_amount: u64,
-        vote_contribution_rate_decimals: u8,
-        uri: String,
-    ) -> Result<()> {
-        let charter = &mut ctx.accounts.charter;
-        charter.authority = ctx.accounts.authority.key();
-        charter.expansion_rate_amount = expansion_rate_amount;
-        charter.expansion_rate_decimals = expansion_rate_decimals;
-        charter.payment_contribution_rate_amount = sol_contribution_rate_amount;
-        charter.payment_contribution_rate_decimals = sol_contribution_rate_decimals;
-        charter.vote_contribution_rate_amount = vote_contribution_rate_amount;
-        charter.vote_contribution_rate_decimals = vote_contribution_rate_decimals;
-        charter.vote_deposit = ctx.accounts.vote_deposit.key();
-        charter.mint = ctx.accounts.mint.key();
-        charter.uri = uri;
-        charter.is_initialized = true;
-
+    pub fn revoke_session(_ctx: Context<RevokeSession>) -> Result<()> {
         Ok(())
     }
 
-    pub fn set_listing_price(ctx: Context<SetListing>, price: u64) -> Result<()> {
-        if ctx.accounts.user.key() != ctx.accounts.listing.authority.key() {
+    pub fn init_code_batch(
+        ctx: Context<InitCodeBatch>,
+        _code_batch_bump: u8,
+        batch_id: u64,
+        root: [u8; 32],
+        size: u32,
+    ) -> Result<()> {
+        let listing = ctx.accounts.listing.clone().into_inner();
+        if ctx.accounts.user.key() != listing.authority {
             return Err(StrangemoodError::UnauthorizedAuthority.into());
         }
-        ctx.accounts.listing.price = price;
-        Ok(())
-    }
-
-    pub fn set_listing_uri(ctx: Context<SetListing>, uri: String) -> Result<()> {
-        if ctx.accounts.user.key() != ctx.accounts.listing.authority.key() {
-            return Err(StrangemoodError::UnauthorizedAuthority.into());
+        if size == 0 || size > MAX_CODE_BATCH_SIZE {
+            return Err(StrangemoodError::InvalidCodeBatchSize.into());
         }
-        ctx.accounts.listing.uri = uri;
-        Ok(())
-    }
 
-    pub fn set_listing_availability(ctx: Context<SetListing>, is_available: bool) -> Result<()> {
-        if ctx.accounts.user.key() != ctx.accounts.listing.authority.key() {
-            return Err(StrangemoodError::UnauthorizedAuthority.into());
+        // The publisher prepays the charter's contribution on every code.
+        let charter = ctx.accounts.charter.clone().into_inner();
+        if charter.is_code_fee_charged {
+            let (treasury, treasury_deposit, payment_deposit, fee_account) = match (
+                ctx.accounts.charter_treasury.as_deref(),
+                ctx.accounts.charter_treasury_deposit.as_deref(),
+                ctx.accounts.listing_payment_deposit.as_deref(),
+                ctx.accounts.fee_token_account.as_deref(),
+            ) {
+                (Some(a), Some(b), Some(c), Some(d)) => (a, b, c, d),
+                _ => return Err(StrangemoodError::CodeFeeRequired.into()),
+            };
+            if treasury_deposit.key() != treasury.deposit {
+                return Err(StrangemoodError::DepositIsNotFoundInCharter.into());
+            }
+            if treasury.mint != payment_deposit.mint || fee_account.mint != treasury.mint {
+                return Err(StrangemoodError::MintNotSupported.into());
+            }
+            let fee =
+                (listing.price as f64 * size as f64 * charter.payment_contribution_rate()) as u64;
+            token_transfer(
+                ctx.accounts.token_program.to_account_info(),
+                fee_account.to_account_info(),
+                treasury_deposit.to_account_info(),
+                ctx.accounts.user.to_account_info(),
+                fee,
+            )?;
         }
-        ctx.accounts.listing.is_available = is_available;
+
+        let code_batch = &mut ctx.accounts.code_batch;
+        code_batch.is_initialized = true;
+        code_batch.listing = ctx.accounts.listing.key();
+        code_batch.batch_id = batch_id;
+        code_batch.root = root;
+        code_batch.size = size;
+        code_batch.redeemed = 0;
+        code_batch.bitmap = vec![0; (size as usize).div_ceil(8)];
         Ok(())
     }
 
-    pub fn set_listing_deposits(ctx: Context<SetListingDeposit>) -> Result<()> {
-        if ctx.accounts.user.key() != ctx.accounts.listing.authority.key() {
+    /// Redeems code number `index`. The code itself never goes on-chain: the
+    /// redeemer proves they hold it by signing `redeem_code_message` with the
+    /// key derived from it, in an ed25519 instruction just before this one.
+    pub fn redeem_code(
+        ctx: Context<RedeemCode>,
+        listing_mint_bump: u8,
+        index: u32,
+        code_key: Pubkey,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let code_batch = &mut ctx.accounts.code_batch;
+        if index >= code_batch.size {
+            return Err(StrangemoodError::InvalidCode.into());
+        }
+        if !verify_merkle_proof(&proof, &code_batch.root, code_leaf(index, &code_key)) {
+            return Err(StrangemoodError::InvalidCode.into());
+        }
+        verify_ed25519_instruction(
+            &ctx.accounts.instructions,
+            &code_key,
+            &redeem_code_message(&code_batch.key(), index, &ctx.accounts.redeemer.key()),
+        )?;
+        if code_batch.is_redeemed(index) {
+            return Err(StrangemoodError::CodeAlreadyRedeemed.into());
+        }
+        code_batch.set_redeemed(index);
+        code_batch.redeemed += 1;
+
+        // Codes can land in a license account that is already frozen.
+        if ctx.accounts.listing_token_account.is_frozen() {
+            thaw_account(
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.listing_mint.to_account_info(),
+                ctx.accounts.listing_token_account.to_account_info(),
+                ctx.accounts.listing_mint_authority.to_account_info(),
+                listing_mint_bump,
+            )?;
+        }
+        mint_to_and_freeze(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.listing_mint.to_account_info(),
+            ctx.accounts.listing_token_account.to_account_info(),
+            ctx.accounts.listing_mint_authority.to_account_info(),
+            listing_mint_bump,
+            1,
+        )
+    }
+
+    pub fn init_distributor(
+        ctx: Context<InitDistributor>,
+        _distributor_bump: u8,
+        distributor_id: u64,
+        root: [u8; 32],
+        max_claims: u32,
+    ) -> Result<()> {
+        if ctx.accounts.user.key() != ctx.accounts.listing.authority.key() {
             return Err(StrangemoodError::UnauthorizedAuthority.into());
         }
-        ctx.accounts.listing.vote_deposit = ctx.accounts.vote_deposit.key();
-        ctx.accounts.listing.payment_deposit = ctx.accounts.payment_deposit.key();
+        if max_claims == 0 || max_claims > MAX_DISTRIBUTOR_CLAIMS {
+            return Err(StrangemoodError::InvalidDistributorSize.into());
+        }
+
+        let distributor = &mut ctx.accounts.distributor;
+        distributor.is_initialized = true;
+        distributor.listing = ctx.accounts.listing.key();
+        distributor.distributor_id = distributor_id;
+        distributor.root = root;
+        distributor.max_claims = max_claims;
+        distributor.claimed = 0;
+        distributor.claimed_quantity = 0;
+        distributor.bitmap = vec![0; (max_claims as usize).div_ceil(8)];
         Ok(())
     }
 
-    pub fn set_listing_authority(ctx: Context<SetListingAuthority>) -> Result<()> {
-        if ctx.accounts.user.key() != ctx.accounts.listing.authority.key() {
+    pub fn claim_license(
+        ctx: Context<ClaimLicense>,
+        listing_mint_bump: u8,
+        index: u32,
+        quantity: u64,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let distributor = &mut ctx.accounts.distributor;
+        if index >= distributor.max_claims || quantity == 0 {
+            return Err(StrangemoodError::InvalidClaim.into());
+        }
+        let leaf = airdrop_leaf(
+            index,
+            &ctx.accounts.claimant.key(),
+            &distributor.listing,
+            quantity,
+        );
+        if !verify_merkle_proof(&proof, &distributor.root, leaf) {
+            return Err(StrangemoodError::InvalidClaim.into());
+        }
+        if distributor.is_claimed(index) {
+            return Err(StrangemoodError::AlreadyClaimed.into());
+        }
+        distributor.set_claimed(index);
+        distributor.claimed += 1;
+        distributor.claimed_quantity += quantity;
+
+        // Claims can land in a license account that is already frozen.
+        if ctx.accounts.listing_token_account.is_frozen() {
+            thaw_account(
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.listing_mint.to_account_info(),
+                ctx.accounts.listing_token_account.to_account_info(),
+                ctx.accounts.listing_mint_authority.to_account_info(),
+                listing_mint_bump,
+            )?;
+        }
+        mint_to_and_freeze(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.listing_mint.to_account_info(),
+            ctx.accounts.listing_token_account.to_account_info(),
+            ctx.accounts.listing_mint_authority.to_account_info(),
+            listing_mint_bump,
+            quantity,
+        )
+    }
+
+    pub fn revoke_license(
+        ctx: Context<RevokeLicense>,
+        _revocation_bump: u8,
+        listing_mint_bump: u8,
+        uri: String,
+    ) -> Result<()> {
+        let authority = ctx.accounts.authority.key();
+        if authority != ctx.accounts.listing.authority
+            && authority != ctx.accounts.charter.authority
+        {
             return Err(StrangemoodError::UnauthorizedAuthority.into());
         }
-        ctx.accounts.listing.authority = ctx.accounts.authority.key();
+
+        if !ctx.accounts.listing_token_account.is_frozen() {
+            freeze_account(
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.listing_mint.to_account_info(),
+                ctx.accounts.listing_token_account.to_account_info(),
+                ctx.accounts.listing_mint_authority.to_account_info(),
+                listing_mint_bump,
+            )?;
+        }
+
+        let revoked_at = Clock::get()?.unix_timestamp;
+        let revocation = &mut ctx.accounts.revocation;
+        revocation.is_initialized = true;
+        revocation.listing = ctx.accounts.listing.key();
+        revocation.listing_token_account = ctx.accounts.listing_token_account.key();
+        revocation.is_revoked = true;
+        revocation.revoked_at = revoked_at;
+        revocation.authority = authority;
+        revocation.uri = uri.clone();
+
+        emit!(LicenseRevoked {
+            listing: revocation.listing,
+            listing_token_account: revocation.listing_token_account,
+            owner: ctx.accounts.listing_token_account.owner,
+            authority,
+            uri,
+        });
+
         Ok(())
     }
 
-    pub fn set_listing_charter(ctx: Context<SetListingCharter>) -> Result<()> {
-        if ctx.accounts.user.key() != ctx.accounts.listing.authority.key() {
+    pub fn reinstate_license(ctx: Context<ReinstateLicense>) -> Result<()> {
+        let authority = ctx.accounts.authority.key();
+        if authority != ctx.accounts.listing.authority
+            && authority != ctx.accounts.charter.authority
+        {
             return Err(StrangemoodError::UnauthorizedAuthority.into());
         }
-        ctx.accounts.listing.charter = ctx.accounts.charter.key();
+
+        let revocation = &mut ctx.accounts.revocation;
+        if !revocation.is_revoked {
+            return Err(StrangemoodError::LicenseNotRevoked.into());
+        }
+        revocation.is_revoked = false;
+        revocation.authority = authority;
+
+        emit!(LicenseReinstated {
+            listing: revocation.listing,
+            listing_token_account: revocation.listing_token_account,
+            authority,
+        });
+
         Ok(())
     }
 
-    pub fn set_charter_expansion_rate(
-        ctx: Context<SetCharter>,
-        expansion_rate_amount: u64,
-        expansion_rate_decimals: u8,
+    pub fn set_license_expiry(
+        ctx: Context<SetLicenseExpiry>,
+        _revocation_bump: u8,
+        expires_at: i64,
     ) -> Result<()> {
-        if ctx.accounts.user.key() != ctx.accounts.charter.authority.key() {
+        let authority = ctx.accounts.authority.key();
+        if authority != ctx.accounts.listing.authority
+            && authority != ctx.accounts.charter.authority
+        {
             return Err(StrangemoodError::UnauthorizedAuthority.into());
         }
-        ctx.accounts.charter.expansion_rate_amount = expansion_rate_amount;
-        ctx.accounts.charter.expansion_rate_decimals = expansion_rate_decimals;
+
+        let revocation = &mut ctx.accounts.revocation;
+        revocation.is_initialized = true;
+        revocation.listing = ctx.accounts.listing.key();
+        revocation.listing_token_account = ctx.accounts.listing_token_account.key();
+        revocation.expires_at = expires_at;
+
         Ok(())
     }
 
-    pub fn set_charter_contribution_rate(
-        ctx: Context<SetCharter>,
+    /// Checks whether `holder` owns a valid license for `listing`, and returns
+    /// the result through return data. Other programs gate on it over CPI:
+    ///
+    /// ```ignore
+    /// let cpi_ctx = CpiContext::new(
+    ///     ctx.accounts.strangemood_program.to_account_info(),
+    ///     strangemood_revival::cpi::accounts::VerifyLicense {
+    ///         listing: ctx.accounts.listing.to_account_info(),
+    ///         listing_token_account: ctx.accounts.license.to_account_info(),
+    ///         holder: ctx.accounts.player.to_account_info(),
+    ///         revocation: ctx.accounts.revocation.to_account_info(),
+    ///     },
+    /// );
+    /// let license = strangemood_revival::cpi::verify_license(cpi_ctx, 1)?.get();
+    /// require!(license.is_valid, GameError::NotLicensed);
+    /// ```
+    pub fn verify_license(
+        ctx: Context<VerifyLicense>,
+        min_quantity: u64,
+    ) -> Result<LicenseVerification> {
+        let listing = &ctx.accounts.listing;
+        let token_account = &ctx.accounts.listing_token_account;
+
+        let revocation = &ctx.accounts.revocation;
+        let record = if revocation.owner == &crate::ID && !revocation.data_is_empty() {
+            Some(LicenseRevocation::try_deserialize(
+                &mut &revocation.data.borrow()[..],
+            )?)
+        } else {
+            None
+        };
+        let is_revoked = record.as_ref().is_some_and(|r| r.is_revoked);
+        let expires_at = record.as_ref().map_or(0, |r| r.expires_at);
+        let is_expired = expires_at != 0 && Clock::get()?.unix_timestamp >= expires_at;
+
+        let is_frozen = token_account.is_frozen();
+        let is_valid = token_account.mint == listing.mint
+            && token_account.owner == ctx.accounts.holder.key()
+            && is_frozen
+            && token_account.amount >= min_quantity
+            && !is_revoked
+            && !is_expired;
+
+        Ok(LicenseVerification {
+            is_valid,
+            listing: listing.key(),
+            holder: ctx.accounts.holder.key(),
+            quantity: token_account.amount,
+            is_frozen,
+            is_revoked,
+            expires_at,
+        })
+    }
+
+    pub fn set_receipt_cashable(ctx: Context<SetReceiptCashable>) -> Result<()> {
+        let authority = ctx.accounts.authority.key();
+        if authority != ctx.accounts.listing.authority
+            && !ListingRole::grants(
+                ctx.accounts.role.as_deref(),
+                &ctx.accounts.listing.key(),
+                ctx.accounts.listing.role_version,
+                &authority,
+                ROLE_SUPPORT,
+            )
+        {
+            return Err(StrangemoodError::UnauthorizedAuthority.into());
+        }
+
+        let receipt = &mut ctx.accounts.receipt;
+        receipt.is_cashable = true;
+
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn init_charter(
+        ctx: Context<InitCharter>,
+        _charter_bump: u8,
+        expansion_rate_amount: u64,
+        expansion_rate_decimals: u8,
         sol_contribution_rate_amount: u64,
         sol_contribution_rate_decimals: u8,
         vote_contribution_rate_amount: u64,
         vote_contribution_rate_decimals: u8,
+        uri: String,
     ) -> Result<()> {
-        if ctx.accounts.user.key() != ctx.accounts.charter.authority.key() {
+        check_rate(
+            sol_contribution_rate_amount,
+            sol_contribution_rate_decimals,
+            10_000,
+        )?;
+        check_rate(
+            vote_contribution_rate_amount,
+            vote_contribution_rate_decimals,
+            10_000,
+        )?;
+        check_decimals(expansion_rate_decimals)?;
+
+        let charter = &mut ctx.accounts.charter;
+        charter.authority = ctx.accounts.authority.key();
+        charter.expansion_rate_amount = expansion_rate_amount;
+        charter.expansion_rate_decimals = expansion_rate_decimals;
+        charter.payment_contribution_rate_amount = sol_contribution_rate_amount;
+        charter.payment_contribution_rate_decimals = sol_contribution_rate_decimals;
+        charter.vote_contribution_rate_amount = vote_contribution_rate_amount;
+        charter.vote_contribution_rate_decimals = vote_contribution_rate_decimals;
+        charter.vote_deposit = ctx.accounts.vote_deposit.key();
+        charter.mint = ctx.accounts.mint.key();
+        charter.uri = uri;
+        charter.payment_contribution_cap_bps = 10_000;
+        charter.vote_contribution_cap_bps = 10_000;
+        charter.buyer_vote_cap_bps = 10_000;
+        charter.emission_decay_bps = 10_000;
+        charter.emission_epoch_cap = u64::MAX;
+        charter.emission_lifetime_cap = u64::MAX;
+        charter.emission_started_at = Clock::get()?.unix_timestamp;
+        charter.is_initialized = true;
+
+        Ok(())
+    }
+
+    pub fn set_listing_price(ctx: Context<SetListing>, price: u64) -> Result<()> {
+        ctx.accounts.authorize(ROLE_PRICE_MANAGER)?;
+        ctx.accounts.listing.price = price;
+        Ok(())
+    }
+
+    pub fn set_listing_uri(ctx: Context<SetListing>, uri: String) -> Result<()> {
+        ctx.accounts.authorize(ROLE_METADATA_EDITOR)?;
+        ctx.accounts.listing.uri = uri;
+        Ok(())
+    }
+
+    pub fn set_listing_availability(ctx: Context<SetListing>, is_available: bool) -> Result<()> {
+        if ctx.accounts.user.key() != ctx.accounts.listing.authority.key() {
             return Err(StrangemoodError::UnauthorizedAuthority.into());
         }
-        ctx.accounts.charter.payment_contribution_rate_amount = sol_contribution_rate_amount;
-        ctx.accounts.charter.payment_contribution_rate_decimals = sol_contribution_rate_decimals;
-        ctx.accounts.charter.vote_contribution_rate_amount = vote_contribution_rate_amount;
-        ctx.accounts.charter.vote_contribution_rate_decimals = vote_contribution_rate_decimals;
+        ctx.accounts.listing.is_available = is_available;
         Ok(())
     }
 
-    pub fn set_charter_authority(ctx: Context<SetCharterAuthority>) -> Result<()> {
-        if ctx.accounts.user.key() != ctx.accounts.charter.authority.key() {
+    pub fn set_listing_refund_policy(
+        ctx: Context<SetListing>,
+        refund_fee_bps: u16,
+        refund_window: i64,
+        is_refund_fee_decaying: bool,
+    ) -> Result<()> {
+        if ctx.accounts.user.key() != ctx.accounts.listing.authority.key() {
             return Err(StrangemoodError::UnauthorizedAuthority.into());
         }
-        ctx.accounts.charter.authority = ctx.accounts.authority.key();
+        if refund_fee_bps > 10_000
+            || refund_window < 0
+            || (is_refund_fee_decaying && refund_window == 0)
+        {
+            return Err(StrangemoodError::InvalidRefundPolicy.into());
+        }
+        ctx.accounts.listing.refund_fee_bps = refund_fee_bps;
+        ctx.accounts.listing.refund_window = refund_window;
+        ctx.accounts.listing.is_refund_fee_decaying = is_refund_fee_decaying;
         Ok(())
     }
 
-    pub fn set_charter_vote_deposit(ctx: Context<SetCharterVoteDeposit>) -> Result<()> {
-        if ctx.accounts.user.key() != ctx.accounts.charter.authority.key() {
+    pub fn set_listing_deposits(ctx: Context<SetListingDeposit>) -> Result<()> {
+        if ctx.accounts.user.key() != ctx.accounts.listing.authority.key() {
             return Err(StrangemoodError::UnauthorizedAuthority.into());
         }
-        ctx.accounts.charter.vote_deposit = ctx.accounts.vote_deposit.key();
+        if ctx.accounts.listing.is_milestone_funded
+            && ctx.accounts.listing.payment_deposit != ctx.accounts.payment_deposit.key()
+        {
+            return Err(StrangemoodError::MilestonesLocked.into());
+        }
+        ctx.accounts.listing.vote_deposit = ctx.accounts.vote_deposit.key();
+        ctx.accounts.listing.payment_deposit = ctx.accounts.payment_deposit.key();
         Ok(())
     }
 
-    pub fn init_charter_treasury(
-        ctx: Context<InitCharterTreasury>,
-        _treasury_bump: u8,
-        expansion_scalar_amount: u64,
-        expansion_scalar_decimals: u8,
-    ) -> Result<()> {
-        let treasury = &mut ctx.accounts.treasury;
-        treasury.charter = ctx.accounts.charter.key();
-        treasury.deposit = ctx.accounts.deposit.key();
-        treasury.mint = ctx.accounts.mint.key();
-        treasury.expansion_scalar_amount = expansion_scalar_amount;
-        treasury.expansion_scalar_decimals = expansion_scalar_decimals;
-        treasury.is_initialized = true;
+    /// Nominates a new listing authority, who takes over with `accept_listing_authority`.
+    pub fn set_listing_authority(ctx: Context<SetListingAuthority>) -> Result<()> {
+        if ctx.accounts.user.key() != ctx.accounts.listing.authority.key() {
+            return Err(StrangemoodError::UnauthorizedAuthority.into());
+        }
+        ctx.accounts.listing.pending_authority = ctx.accounts.authority.key();
+        Ok(())
+    }
 
+    pub fn accept_listing_authority(ctx: Context<AcceptListingAuthority>) -> Result<()> {
+        let listing = &mut ctx.accounts.listing;
+        if listing.pending_authority == Pubkey::default()
+            || ctx.accounts.authority.key() != listing.pending_authority
+        {
+            return Err(StrangemoodError::UnauthorizedAuthority.into());
+        }
+        listing.authority = listing.pending_authority;
+        listing.pending_authority = Pubkey::default();
+        listing.role_version += 1;
         Ok(())
     }
 
-    pub fn set_charter_treasury_expansion_scalar(
-        ctx: Context<SetCharterTreasuryExpansionScalar>,
-        expansion_scalar_amount: u64,
-        expansion_scalar_decimals: u8,
+    pub fn cancel_listing_authority(ctx: Context<CancelListingAuthority>) -> Result<()> {
+        if ctx.accounts.user.key() != ctx.accounts.listing.authority.key() {
+            return Err(StrangemoodError::UnauthorizedAuthority.into());
+        }
+        ctx.accounts.listing.pending_authority = Pubkey::default();
+        Ok(())
+    }
+
+    pub fn grant_listing_role(
+        ctx: Context<GrantListingRole>,
+        _role_bump: u8,
+        roles: u8,
     ) -> Result<()> {
-        let treasury = &mut ctx.accounts.treasury;
-        treasury.expansion_scalar_amount = expansion_scalar_amount;
-        treasury.expansion_scalar_decimals = expansion_scalar_decimals;
+        if ctx.accounts.user.key() != ctx.accounts.listing.authority.key() {
+            return Err(StrangemoodError::UnauthorizedAuthority.into());
+        }
+        if roles & !ROLE_ALL != 0 {
+            return Err(StrangemoodError::InvalidRole.into());
+        }
+        let role = &mut ctx.accounts.role;
+        let role_version = ctx.accounts.listing.role_version;
+        if role.version != role_version {
+            role.roles = 0;
+            role.version = role_version;
+        }
+        role.is_initialized = true;
+        role.listing = ctx.accounts.listing.key();
+        role.grantee = ctx.accounts.grantee.key();
+        role.roles |= roles;
+        Ok(())
+    }
 
+    pub fn revoke_listing_role(ctx: Context<RevokeListingRole>, roles: u8) -> Result<()> {
+        if ctx.accounts.user.key() != ctx.accounts.listing.authority.key() {
+            return Err(StrangemoodError::UnauthorizedAuthority.into());
+        }
+        ctx.accounts.role.roles &= !roles;
         Ok(())
     }
 
-    pub fn set_charter_treasury_deposit(ctx: Context<SetCharterTreasuryDeposit>) -> Result<()> {
-        let treasury = &mut ctx.accounts.treasury;
-        treasury.deposit = ctx.accounts.deposit.key();
+    pub fn set_listing_pricing_key(ctx: Context<SetListingPricingKey>) -> Result<()> {
+        if ctx.accounts.user.key() != ctx.accounts.listing.authority.key() {
+            return Err(StrangemoodError::UnauthorizedAuthority.into());
+        }
+        ctx.accounts.listing.pricing_key = ctx.accounts.pricing_key.key();
+        Ok(())
+    }
 
+    pub fn set_listing_attestation_key(ctx: Context<SetListingAttestationKey>) -> Result<()> {
+        if ctx.accounts.user.key() != ctx.accounts.listing.authority.key() {
+            return Err(StrangemoodError::UnauthorizedAuthority.into());
+        }
+        ctx.accounts.listing.attestation_key = ctx.accounts.attestation_key.key();
         Ok(())
     }
-}
 
-// ─── Account Structs ────────────────────────────────────────────────────────
+    pub fn set_listing_charter(ctx: Context<SetListingCharter>) -> Result<()> {
+        if ctx.accounts.user.key() != ctx.accounts.listing.authority.key() {
+            return Err(StrangemoodError::UnauthorizedAuthority.into());
+        }
+        // Open disputes are resolved by the charter they were opened under.
+        if ctx.accounts.listing.open_disputes > 0 {
+            return Err(StrangemoodError::ListingHasOpenDisputes.into());
+        }
+        ctx.accounts.listing.charter = ctx.accounts.charter.key();
+        Ok(())
+    }
 
-#[derive(Accounts)]
-#[instruction(receipt_nonce: u128, receipt_bump: u8, listing_mint_bump: u8, escrow_authority_bump: u8)]
-pub struct Purchase<'info> {
-    #[account(mut)]
-    pub purchase_token_account: Box<Account<'info, TokenAccount>>,
+    pub fn init_listing_price_table(
+        ctx: Context<InitListingPriceTable>,
+        _price_table_bump: u8,
+    ) -> Result<()> {
+        if ctx.accounts.user.key() != ctx.accounts.listing.authority.key() {
+            return Err(StrangemoodError::UnauthorizedAuthority.into());
+        }
 
-    #[account(
-        constraint = listing_payment_deposit.key() == listing.clone().into_inner().payment_deposit.key(),
-        constraint = listing_mint.key() == listing.clone().into_inner().mint.key(),
-    )]
-    pub listing: Box<Account<'info, Listing>>,
+        let price_table = &mut ctx.accounts.price_table;
+        price_table.is_initialized = true;
+        price_table.listing = ctx.accounts.listing.key();
+        price_table.entries = Vec::new();
+        ctx.accounts.listing.has_price_table = true;
 
-    #[account(
-        constraint = listing_payment_deposit.mint == listing_payment_deposit_mint.key()
-    )]
-    pub listing_payment_deposit: Box<Account<'info, TokenAccount>>,
+        Ok(())
+    }
 
-    pub listing_payment_deposit_mint: Account<'info, Mint>,
+    pub fn set_listing_price_table_entry(
+        ctx: Context<SetListingPriceTableEntry>,
+        price: u64,
+        oracle: Option<OracleConfig>,
+    ) -> Result<()> {
+        if ctx.accounts.user.key() != ctx.accounts.listing.authority.key() {
+            return Err(StrangemoodError::UnauthorizedAuthority.into());
+        }
 
-    /// CHECK: cashier is stored in receipt, validated during cash instruction
-    pub cashier: AccountInfo<'info>,
+        let entry = PriceTableEntry {
+            mint: ctx.accounts.payment_deposit.mint,
+            price,
+            payment_deposit: ctx.accounts.payment_deposit.key(),
+            oracle,
+        };
+
+        let price_table = &mut ctx.accounts.price_table;
+        match price_table
+            .entries
+            .iter_mut()
+            .find(|e| e.mint == entry.mint)
+        {
+            Some(existing) => *existing = entry,
+            None => {
+                if price_table.entries.len() >= MAX_PRICE_TABLE_ENTRIES {
+                    return Err(StrangemoodError::PriceTableFull.into());
+                }
+                price_table.entries.push(entry);
+            }
+        }
 
-    #[account(mut)]
-    pub listing_token_account: Account<'info, TokenAccount>,
+        Ok(())
+    }
 
-    #[account(mut)]
-    pub listing_mint: Box<Account<'info, Mint>>,
+    pub fn remove_listing_price_table_entry(
+        ctx: Context<RemoveListingPriceTableEntry>,
+    ) -> Result<()> {
+        if ctx.accounts.user.key() != ctx.accounts.listing.authority.key() {
+            return Err(StrangemoodError::UnauthorizedAuthority.into());
+        }
 
-    /// CHECK: PDA seed validation via seeds constraint
-    #[account(
-        seeds = [b"mint", listing_mint.key().as_ref()],
-        bump = listing_mint_bump,
-    )]
-    pub listing_mint_authority: AccountInfo<'info>,
+        let mint = ctx.accounts.mint.key();
+        let price_table = &mut ctx.accounts.price_table;
+        let len = price_table.entries.len();
+        price_table.entries.retain(|e| e.mint != mint);
+        if price_table.entries.len() == len {
+            return Err(StrangemoodError::MintNotSupported.into());
+        }
+
+        Ok(())
+    }
+
+    pub fn init_campaign(
+        ctx: Context<InitCampaign>,
+        _campaign_bump: u8,
+        goal: u64,
+        deadline: i64,
+    ) -> Result<()> {
+        if ctx.accounts.user.key() != ctx.accounts.listing.authority.key() {
+            return Err(StrangemoodError::UnauthorizedAuthority.into());
+        }
+        if deadline <= Clock::get()?.unix_timestamp {
+            return Err(StrangemoodError::CampaignEnded.into());
+        }
+        // Receipts from before the campaign were never counted toward it.
+        if ctx.accounts.listing.open_receipts > 0 {
+            return Err(StrangemoodError::ListingHasOpenReceipts.into());
+        }
+
+        let campaign = &mut ctx.accounts.campaign;
+        campaign.is_initialized = true;
+        campaign.listing = ctx.accounts.listing.key();
+        campaign.mint = ctx.accounts.mint.key();
+        campaign.goal = goal;
+        campaign.deadline = deadline;
+
+        ctx.accounts.listing.is_campaign = true;
+
+        Ok(())
+    }
+
+    pub fn init_milestones(
+        ctx: Context<InitMilestones>,
+        _milestones_bump: u8,
+        release_bps: Vec<u16>,
+    ) -> Result<()> {
+        if ctx.accounts.user.key() != ctx.accounts.listing.authority.key() {
+            return Err(StrangemoodError::UnauthorizedAuthority.into());
+        }
+        if release_bps.is_empty()
+            || release_bps.len() > MAX_MILESTONES
+            || release_bps.iter().map(|bps| *bps as u64).sum::<u64>() != 10_000
+        {
+            return Err(StrangemoodError::InvalidMilestones.into());
+        }
+
+        let milestones = &mut ctx.accounts.milestones;
+        milestones.is_initialized = true;
+        milestones.listing = ctx.accounts.listing.key();
+        milestones.vault = ctx.accounts.vault.key();
+        milestones.beneficiary = ctx.accounts.payment_deposit.key();
+        milestones.milestones = release_bps
+            .into_iter()
+            .map(|release_bps| Milestone {
+                release_bps,
+                is_approved: false,
+            })
+            .collect();
+
+        let listing = &mut ctx.accounts.listing;
+        listing.payment_deposit = ctx.accounts.vault.key();
+        listing.is_milestone_funded = true;
+
+        Ok(())
+    }
+
+    /// Approves a milestone at the charter authority's request, or by consuming
+    /// an `ApproveMilestone` change a passed proposal queued.
+    pub fn approve_milestone(
+        ctx: Context<ApproveMilestone>,
+        milestones_bump: u8,
+        index: u8,
+    ) -> Result<()> {
+        let change = CharterChange::ApproveMilestone {
+            milestones: ctx.accounts.milestones.key(),
+            index,
+        };
+        check_charter_decision(
+            &ctx.accounts.charter,
+            ctx.accounts.pending.as_deref().map(|p| &**p),
+            &ctx.accounts.user.key(),
+            change,
+        )?;
+
+        let milestones = &mut ctx.accounts.milestones;
+        if milestones.is_abandoned {
+            return Err(StrangemoodError::MilestonesAbandoned.into());
+        }
+        match milestones.milestones.get_mut(index as usize) {
+            Some(milestone) if !milestone.is_approved => milestone.is_approved = true,
+            _ => return Err(StrangemoodError::InvalidMilestones.into()),
+        }
+
+        let amount = milestones.releasable();
+        milestones.released += amount;
+
+        let listing = ctx.accounts.listing.key();
+        token_signed_transfer(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.vault.to_account_info(),
+            ctx.accounts.beneficiary.to_account_info(),
+            ctx.accounts.milestones.to_account_info(),
+            amount,
+            &[b"milestones", listing.as_ref(), &[milestones_bump]],
+        )
+    }
+
+    pub fn release_milestone_funds(
+        ctx: Context<ReleaseMilestoneFunds>,
+        milestones_bump: u8,
+    ) -> Result<()> {
+        let milestones = &mut ctx.accounts.milestones;
+        if milestones.is_abandoned {
+            return Err(StrangemoodError::MilestonesAbandoned.into());
+        }
+
+        let amount = milestones.releasable();
+        milestones.released += amount;
+
+        let listing = milestones.listing;
+        token_signed_transfer(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.vault.to_account_info(),
+            ctx.accounts.beneficiary.to_account_info(),
+            ctx.accounts.milestones.to_account_info(),
+            amount,
+            &[b"milestones", listing.as_ref(), &[milestones_bump]],
+        )
+    }
+
+    /// Abandons a listing's milestones at the charter authority's request, or by
+    /// consuming an `AbandonMilestones` change a passed proposal queued.
+    pub fn abandon_milestones(ctx: Context<AbandonMilestones>) -> Result<()> {
+        let change = CharterChange::AbandonMilestones {
+            milestones: ctx.accounts.milestones.key(),
+        };
+        check_charter_decision(
+            &ctx.accounts.charter,
+            ctx.accounts.pending.as_deref().map(|p| &**p),
+            &ctx.accounts.user.key(),
+            change,
+        )?;
+
+        let milestones = &mut ctx.accounts.milestones;
+        if milestones.is_abandoned {
+            return Err(StrangemoodError::MilestonesAbandoned.into());
+        }
+        milestones.is_abandoned = true;
+        milestones.refund_pool = ctx.accounts.vault.amount;
+
+        ctx.accounts.listing.is_available = false;
+
+        Ok(())
+    }
+
+    pub fn claim_milestone_refund(
+        ctx: Context<ClaimMilestoneRefund>,
+        milestones_bump: u8,
+        listing_mint_bump: u8,
+        quantity: u64,
+    ) -> Result<()> {
+        let milestones = &mut ctx.accounts.milestones;
+        if !milestones.is_abandoned {
+            return Err(StrangemoodError::MilestonesNotAbandoned.into());
+        }
+        // Only licenses the holder cashed into the vault earn a share.
+        let backing = &mut ctx.accounts.backing;
+        if quantity == 0 || backing.refunded_licenses + quantity > backing.licenses {
+            return Err(StrangemoodError::InvalidQuantity.into());
+        }
+        backing.refunded_licenses += quantity;
+
+        let amount = (milestones.refund_pool as u128 * quantity as u128
+            / milestones.licenses as u128) as u64;
+        milestones.refunded_licenses += quantity;
+
+        // Licenses are frozen once minted, so thaw before the holder burns them
+        // and freeze whatever's left afterwards.
+        let is_frozen = ctx.accounts.listing_token_account.is_frozen();
+        if is_frozen {
+            thaw_account(
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.listing_mint.to_account_info(),
+                ctx.accounts.listing_token_account.to_account_info(),
+                ctx.accounts.listing_mint_authority.to_account_info(),
+                listing_mint_bump,
+            )?;
+        }
+        holder_burn(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.listing_mint.to_account_info(),
+            ctx.accounts.listing_token_account.to_account_info(),
+            ctx.accounts.holder.to_account_info(),
+            quantity,
+        )?;
+        if is_frozen && ctx.accounts.listing_token_account.amount > quantity {
+            freeze_account(
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.listing_mint.to_account_info(),
+                ctx.accounts.listing_token_account.to_account_info(),
+                ctx.accounts.listing_mint_authority.to_account_info(),
+                listing_mint_bump,
+            )?;
+        }
+
+        let listing = milestones.listing;
+        token_signed_transfer(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.vault.to_account_info(),
+            ctx.accounts.return_deposit.to_account_info(),
+            ctx.accounts.milestones.to_account_info(),
+            amount,
+            &[b"milestones", listing.as_ref(), &[milestones_bump]],
+        )
+    }
+
+    pub fn set_charter_expansion_rate(
+        ctx: Context<SetCharter>,
+        _pending_bump: u8,
+        expansion_rate_amount: u64,
+        expansion_rate_decimals: u8,
+    ) -> Result<()> {
+        queue_charter_change(
+            &mut ctx.accounts.charter,
+            &mut ctx.accounts.pending,
+            &ctx.accounts.user,
+            CharterChange::ExpansionRate {
+                amount: expansion_rate_amount,
+                decimals: expansion_rate_decimals,
+            },
+        )
+    }
+
+    pub fn set_charter_contribution_rate(
+        ctx: Context<SetCharter>,
+        _pending_bump: u8,
+        sol_contribution_rate_amount: u64,
+        sol_contribution_rate_decimals: u8,
+        vote_contribution_rate_amount: u64,
+        vote_contribution_rate_decimals: u8,
+    ) -> Result<()> {
+        queue_charter_change(
+            &mut ctx.accounts.charter,
+            &mut ctx.accounts.pending,
+            &ctx.accounts.user,
+            CharterChange::ContributionRate {
+                payment_amount: sol_contribution_rate_amount,
+                payment_decimals: sol_contribution_rate_decimals,
+                vote_amount: vote_contribution_rate_amount,
+                vote_decimals: vote_contribution_rate_decimals,
+            },
+        )
+    }
+
+    pub fn set_charter_buyer_vote_rate(
+        ctx: Context<SetCharter>,
+        _pending_bump: u8,
+        buyer_vote_rate_amount: u64,
+        buyer_vote_rate_decimals: u8,
+    ) -> Result<()> {
+        queue_charter_change(
+            &mut ctx.accounts.charter,
+            &mut ctx.accounts.pending,
+            &ctx.accounts.user,
+            CharterChange::BuyerVoteRate {
+                amount: buyer_vote_rate_amount,
+                decimals: buyer_vote_rate_decimals,
+            },
+        )
+    }
+
+    pub fn set_charter_authority(
+        ctx: Context<SetCharterAuthority>,
+        _pending_bump: u8,
+    ) -> Result<()> {
+        let authority = ctx.accounts.authority.key();
+        queue_charter_change(
+            &mut ctx.accounts.charter,
+            &mut ctx.accounts.pending,
+            &ctx.accounts.user,
+            CharterChange::Authority { authority },
+        )
+    }
+
+    pub fn set_charter_arbiter(ctx: Context<SetCharterArbiter>, _pending_bump: u8) -> Result<()> {
+        let arbiter = ctx.accounts.arbiter.key();
+        queue_charter_change(
+            &mut ctx.accounts.charter,
+            &mut ctx.accounts.pending,
+            &ctx.accounts.user,
+            CharterChange::Arbiter { arbiter },
+        )
+    }
+
+    pub fn set_charter_dispute_policy(
+        ctx: Context<SetCharter>,
+        _pending_bump: u8,
+        dispute_period: i64,
+        is_dispute_default_refund: bool,
+    ) -> Result<()> {
+        queue_charter_change(
+            &mut ctx.accounts.charter,
+            &mut ctx.accounts.pending,
+            &ctx.accounts.user,
+            CharterChange::DisputePolicy {
+                dispute_period,
+                is_dispute_default_refund,
+            },
+        )
+    }
+
+    pub fn set_charter_code_fee(
+        ctx: Context<SetCharter>,
+        _pending_bump: u8,
+        is_code_fee_charged: bool,
+    ) -> Result<()> {
+        queue_charter_change(
+            &mut ctx.accounts.charter,
+            &mut ctx.accounts.pending,
+            &ctx.accounts.user,
+            CharterChange::CodeFee {
+                is_code_fee_charged,
+            },
+        )
+    }
+
+    pub fn set_charter_change_delay(
+        ctx: Context<SetCharter>,
+        _pending_bump: u8,
+        change_delay: i64,
+    ) -> Result<()> {
+        queue_charter_change(
+            &mut ctx.accounts.charter,
+            &mut ctx.accounts.pending,
+            &ctx.accounts.user,
+            CharterChange::ChangeDelay { change_delay },
+        )
+    }
+
+    pub fn set_charter_vote_vesting(
+        ctx: Context<SetCharter>,
+        _pending_bump: u8,
+        cliff: i64,
+        duration: i64,
+    ) -> Result<()> {
+        queue_charter_change(
+            &mut ctx.accounts.charter,
+            &mut ctx.accounts.pending,
+            &ctx.accounts.user,
+            CharterChange::VoteVesting { cliff, duration },
+        )
+    }
+
+    pub fn set_charter_emission_policy(
+        ctx: Context<SetCharter>,
+        _pending_bump: u8,
+        decay_bps: u16,
+        epoch_duration: i64,
+        epoch_cap: u64,
+        lifetime_cap: u64,
+    ) -> Result<()> {
+        queue_charter_change(
+            &mut ctx.accounts.charter,
+            &mut ctx.accounts.pending,
+            &ctx.accounts.user,
+            CharterChange::EmissionPolicy {
+                decay_bps,
+                epoch_duration,
+                epoch_cap,
+                lifetime_cap,
+            },
+        )
+    }
+
+    pub fn set_charter_caps(
+        ctx: Context<SetCharterCaps>,
+        payment_contribution_cap_bps: u16,
+        vote_contribution_cap_bps: u16,
+        buyer_vote_cap_bps: u16,
+        min_change_delay: i64,
+    ) -> Result<()> {
+        if ctx.accounts.user.key() != ctx.accounts.charter.authority.key() {
+            return Err(StrangemoodError::UnauthorizedAuthority.into());
+        }
+        ctx.accounts.charter.tighten_caps(
+            payment_contribution_cap_bps,
+            vote_contribution_cap_bps,
+            buyer_vote_cap_bps,
+            min_change_delay,
+        )
+    }
+
+    pub fn set_charter_vote_deposit(
+        ctx: Context<SetCharterVoteDeposit>,
+        _pending_bump: u8,
+    ) -> Result<()> {
+        let vote_deposit = ctx.accounts.vote_deposit.key();
+        queue_charter_change(
+            &mut ctx.accounts.charter,
+            &mut ctx.accounts.pending,
+            &ctx.accounts.user,
+            CharterChange::VoteDeposit { vote_deposit },
+        )
+    }
+
+    /// Completes a charter authority handover. The nominee signs, unless the
+    /// nominee is the charter's own governance account.
+    pub fn accept_charter_authority(ctx: Context<AcceptCharterAuthority>) -> Result<()> {
+        let charter = &mut ctx.accounts.charter;
+        let nominee = charter.pending_authority;
+        let is_governance = ctx
+            .accounts
+            .governance
+            .as_ref()
+            .is_some_and(|g| g.key() == nominee);
+        let is_signer = ctx
+            .accounts
+            .authority
+            .as_ref()
+            .is_some_and(|a| a.key() == nominee);
+        if nominee == Pubkey::default() || !(is_governance || is_signer) {
+            return Err(StrangemoodError::UnauthorizedAuthority.into());
+        }
+        charter.authority = nominee;
+        charter.pending_authority = Pubkey::default();
+        Ok(())
+    }
+
+    pub fn cancel_charter_authority(ctx: Context<CancelCharterAuthority>) -> Result<()> {
+        if ctx.accounts.user.key() != ctx.accounts.charter.authority.key() {
+            return Err(StrangemoodError::UnauthorizedAuthority.into());
+        }
+        ctx.accounts.charter.pending_authority = Pubkey::default();
+        Ok(())
+    }
+
+    pub fn apply_charter_change(ctx: Context<ApplyCharterChange>) -> Result<()> {
+        let pending = &ctx.accounts.pending;
+        if Clock::get()?.unix_timestamp < pending.effective_at {
+            return Err(StrangemoodError::ChangeNotYetEffective.into());
+        }
+        let change = pending.change;
+        if change.treasury().is_some()
+            && ctx.accounts.treasury.as_ref().map(|t| t.key()) != change.treasury()
+        {
+            return Err(StrangemoodError::TreasuryRequired.into());
+        }
+        // Caps may have tightened since the change was queued.
+        change.validate(&ctx.accounts.charter)?;
+        change.apply(
+            &mut ctx.accounts.charter,
+            ctx.accounts.governance.as_deref_mut().map(|g| &mut **g),
+            ctx.accounts.treasury.as_deref_mut().map(|t| &mut **t),
+        )
+    }
+
+    pub fn cancel_charter_change(ctx: Context<CancelCharterChange>) -> Result<()> {
+        if ctx.accounts.user.key() != ctx.accounts.charter.authority.key() {
+            return Err(StrangemoodError::UnauthorizedAuthority.into());
+        }
+        if Clock::get()?.unix_timestamp >= ctx.accounts.pending.effective_at {
+            return Err(StrangemoodError::ChangeAlreadyEffective.into());
+        }
+        Ok(())
+    }
+
+    pub fn init_charter_treasury(
+        ctx: Context<InitCharterTreasury>,
+        _treasury_bump: u8,
+        expansion_scalar_amount: u64,
+        expansion_scalar_decimals: u8,
+        epoch_duration: i64,
+        epoch_limit: u64,
+    ) -> Result<()> {
+        check_expansion_scalar(expansion_scalar_amount, expansion_scalar_decimals)?;
+        check_treasury_limit(epoch_duration)?;
+
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.charter = ctx.accounts.charter.key();
+        treasury.deposit = ctx.accounts.deposit.key();
+        treasury.mint = ctx.accounts.mint.key();
+        treasury.expansion_scalar_amount = expansion_scalar_amount;
+        treasury.expansion_scalar_decimals = expansion_scalar_decimals;
+        treasury.epoch_duration = epoch_duration;
+        treasury.epoch_limit = epoch_limit;
+        treasury.epoch_started_at = Clock::get()?.unix_timestamp;
+        treasury.epoch_spent = 0;
+        treasury.withdrawal_count = 0;
+        treasury.is_initialized = true;
+
+        Ok(())
+    }
+
+    pub fn set_charter_treasury_expansion_scalar(
+        ctx: Context<SetCharterTreasuryExpansionScalar>,
+        _pending_bump: u8,
+        expansion_scalar_amount: u64,
+        expansion_scalar_decimals: u8,
+    ) -> Result<()> {
+        let treasury = ctx.accounts.treasury.key();
+        queue_charter_change(
+            &mut ctx.accounts.charter,
+            &mut ctx.accounts.pending,
+            &ctx.accounts.authority,
+            CharterChange::TreasuryExpansionScalar {
+                treasury,
+                amount: expansion_scalar_amount,
+                decimals: expansion_scalar_decimals,
+            },
+        )
+    }
+
+    pub fn set_charter_treasury_deposit(
+        ctx: Context<SetCharterTreasuryDeposit>,
+        _pending_bump: u8,
+    ) -> Result<()> {
+        let treasury = ctx.accounts.treasury.key();
+        let deposit = ctx.accounts.deposit.key();
+        queue_charter_change(
+            &mut ctx.accounts.charter,
+            &mut ctx.accounts.pending,
+            &ctx.accounts.authority,
+            CharterChange::TreasuryDeposit { treasury, deposit },
+        )
+    }
+
+    pub fn set_charter_treasury_limit(
+        ctx: Context<SetCharterTreasuryLimit>,
+        _pending_bump: u8,
+        epoch_duration: i64,
+        epoch_limit: u64,
+    ) -> Result<()> {
+        let treasury = ctx.accounts.treasury.key();
+        queue_charter_change(
+            &mut ctx.accounts.charter,
+            &mut ctx.accounts.pending,
+            &ctx.accounts.authority,
+            CharterChange::TreasuryLimit {
+                treasury,
+                epoch_duration,
+                epoch_limit,
+            },
+        )
+    }
+
+    /// Pays out of a treasury vault, either at the charter authority's request or
+    /// by consuming a `TreasuryWithdrawal` change a passed proposal queued. Both
+    /// count against the treasury's per-epoch limit.
+    pub fn withdraw_from_treasury(
+        ctx: Context<WithdrawFromTreasury>,
+        treasury_bump: u8,
+        amount: u64,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let treasury_key = ctx.accounts.treasury.key();
+        let destination = ctx.accounts.destination.key();
+
+        let change_id = match ctx.accounts.pending.as_deref() {
+            Some(pending) => {
+                if now < pending.effective_at {
+                    return Err(StrangemoodError::ChangeNotYetEffective.into());
+                }
+                if pending.change
+                    != (CharterChange::TreasuryWithdrawal {
+                        treasury: treasury_key,
+                        destination,
+                        amount,
+                    })
+                {
+                    return Err(StrangemoodError::UnexpectedCharterChange.into());
+                }
+                Some(pending.id)
+            }
+            None => {
+                if ctx.accounts.user.key() != ctx.accounts.charter.authority.key() {
+                    return Err(StrangemoodError::UnauthorizedAuthority.into());
+                }
+                None
+            }
+        };
+
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.spend(amount, now)?;
+        let charter = treasury.charter;
+        let mint = treasury.mint;
+        token_signed_transfer(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.deposit.to_account_info(),
+            ctx.accounts.destination.to_account_info(),
+            treasury.to_account_info(),
+            amount,
+            &[
+                b"treasury",
+                charter.as_ref(),
+                mint.as_ref(),
+                &[treasury_bump],
+            ],
+        )?;
+
+        emit!(TreasuryWithdrawn {
+            charter,
+            treasury: treasury_key,
+            deposit: ctx.accounts.deposit.key(),
+            destination,
+            amount,
+            authority: ctx.accounts.user.key(),
+            change_id,
+            withdrawal_id: treasury.withdrawal_count - 1,
+            epoch_started_at: treasury.epoch_started_at,
+            epoch_spent: treasury.epoch_spent,
+        });
+        Ok(())
+    }
+
+    /// Sends a listing's vested votes to its vote deposit. Anyone can call it.
+    pub fn claim_vested_votes(ctx: Context<ClaimVestedVotes>, vesting_bump: u8) -> Result<()> {
+        let amount = ctx.accounts.vesting.claim(Clock::get()?.unix_timestamp);
+        let listing = ctx.accounts.listing.key();
+        let mint = ctx.accounts.vesting.mint;
+        token_signed_transfer(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.vault.to_account_info(),
+            ctx.accounts.listing_vote_deposit.to_account_info(),
+            ctx.accounts.vesting.to_account_info(),
+            amount,
+            &[b"vesting", listing.as_ref(), mint.as_ref(), &[vesting_bump]],
+        )
+    }
+
+    pub fn init_governance(
+        ctx: Context<InitGovernance>,
+        _governance_bump: u8,
+        quorum: u64,
+        threshold_bps: u16,
+        voting_period: i64,
+    ) -> Result<()> {
+        let governance = &mut ctx.accounts.governance;
+        governance.is_initialized = true;
+        governance.charter = ctx.accounts.charter.key();
+        governance.vault = ctx.accounts.vault.key();
+        governance.proposal_count = 0;
+        governance.set_voting_rules(quorum, threshold_bps, voting_period)
+    }
+
+    pub fn create_proposal(
+        ctx: Context<CreateProposal>,
+        _proposal_bump: u8,
+        change: CharterChange,
+        uri: String,
+    ) -> Result<()> {
+        let governance = &mut ctx.accounts.governance;
+        let now = Clock::get()?.unix_timestamp;
+
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.is_initialized = true;
+        proposal.governance = governance.key();
+        proposal.proposer = ctx.accounts.proposer.key();
+        proposal.id = governance.proposal_count;
+        proposal.change = change;
+        proposal.uri = uri;
+        proposal.created_at = now;
+        proposal.voting_ends_at = now + governance.voting_period;
+
+        governance.proposal_count += 1;
+        Ok(())
+    }
+
+    pub fn cast_vote(
+        ctx: Context<CastVote>,
+        _vote_bump: u8,
+        amount: u64,
+        is_approve: bool,
+    ) -> Result<()> {
+        let proposal = &mut ctx.accounts.proposal;
+        if Clock::get()?.unix_timestamp >= proposal.voting_ends_at {
+            return Err(StrangemoodError::VotingClosed.into());
+        }
+        if amount == 0 {
+            return Err(StrangemoodError::InvalidQuantity.into());
+        }
+
+        // Votes are weighted by charter tokens locked in the vault until voting ends.
+        token_transfer(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.voter_token_account.to_account_info(),
+            ctx.accounts.vault.to_account_info(),
+            ctx.accounts.voter.to_account_info(),
+            amount,
+        )?;
+        if is_approve {
+            proposal.yes_votes += amount;
+        } else {
+            proposal.no_votes += amount;
+        }
+
+        let vote = &mut ctx.accounts.vote;
+        vote.is_initialized = true;
+        vote.proposal = proposal.key();
+        vote.voter = ctx.accounts.voter.key();
+        vote.amount = amount;
+        vote.is_approve = is_approve;
+        Ok(())
+    }
+
+    pub fn withdraw_vote(ctx: Context<WithdrawVote>, governance_bump: u8) -> Result<()> {
+        if Clock::get()?.unix_timestamp < ctx.accounts.proposal.voting_ends_at {
+            return Err(StrangemoodError::VotingInProgress.into());
+        }
+        let charter = ctx.accounts.governance.charter;
+        token_signed_transfer(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.vault.to_account_info(),
+            ctx.accounts.voter_token_account.to_account_info(),
+            ctx.accounts.governance.to_account_info(),
+            ctx.accounts.vote.amount,
+            &[b"governance", charter.as_ref(), &[governance_bump]],
+        )
+    }
+
+    pub fn execute_proposal(ctx: Context<ExecuteProposal>, _pending_bump: u8) -> Result<()> {
+        let governance = &ctx.accounts.governance;
+        let proposal = &mut ctx.accounts.proposal;
+        if ctx.accounts.charter.authority != governance.key() {
+            return Err(StrangemoodError::UnauthorizedAuthority.into());
+        }
+        if proposal.is_executed {
+            return Err(StrangemoodError::ProposalExecuted.into());
+        }
+        if Clock::get()?.unix_timestamp < proposal.voting_ends_at {
+            return Err(StrangemoodError::VotingInProgress.into());
+        }
+        governance.check_passed(proposal)?;
+        proposal.is_executed = true;
+
+        // Passed proposals wait out the charter's change delay like any other change.
+        let change = proposal.change;
+        let key = ctx.accounts.charter.key();
+        let payer = ctx.accounts.executor.key();
+        ctx.accounts
+            .charter
+            .queue_change(key, &mut ctx.accounts.pending, change, payer)
+    }
+
+    pub fn create_multisig(
+        ctx: Context<CreateMultisig>,
+        _multisig_bump: u8,
+        members: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        let multisig = &mut ctx.accounts.multisig;
+        multisig.is_initialized = true;
+        multisig.create_key = ctx.accounts.create_key.key();
+        multisig.transaction_count = 0;
+        multisig.set_members(members, threshold)
+    }
+
+    /// Only callable by the multisig itself, through `execute_transaction`.
+    pub fn set_multisig_members(
+        ctx: Context<SetMultisigMembers>,
+        _signer_bump: u8,
+        members: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        ctx.accounts.multisig.set_members(members, threshold)
+    }
+
+    pub fn propose_transaction(
+        ctx: Context<ProposeTransaction>,
+        _transaction_bump: u8,
+        accounts: Vec<TransactionAccount>,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        let multisig = &mut ctx.accounts.multisig;
+        let member = multisig.member_index(&ctx.accounts.proposer.key())?;
+
+        let transaction = &mut ctx.accounts.transaction;
+        transaction.is_initialized = true;
+        transaction.multisig = multisig.key();
+        transaction.proposer = ctx.accounts.proposer.key();
+        transaction.index = multisig.transaction_count;
+        transaction.version = multisig.version;
+        transaction.accounts = accounts;
+        transaction.data = data;
+        transaction.approvals = 1 << member;
+        transaction.is_executed = false;
+
+        multisig.transaction_count += 1;
+        Ok(())
+    }
+
+    pub fn approve_transaction(ctx: Context<ApproveTransaction>) -> Result<()> {
+        let multisig = &ctx.accounts.multisig;
+        let transaction = &mut ctx.accounts.transaction;
+        if transaction.version != multisig.version {
+            return Err(StrangemoodError::StaleTransaction.into());
+        }
+        let member = multisig.member_index(&ctx.accounts.member.key())?;
+        transaction.approvals |= 1 << member;
+        Ok(())
+    }
+
+    pub fn execute_transaction<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteTransaction<'info>>,
+        signer_bump: u8,
+    ) -> Result<()> {
+        let multisig = &ctx.accounts.multisig;
+        let transaction = &mut ctx.accounts.transaction;
+        multisig.member_index(&ctx.accounts.member.key())?;
+        if transaction.is_executed {
+            return Err(StrangemoodError::TransactionExecuted.into());
+        }
+        if transaction.version != multisig.version {
+            return Err(StrangemoodError::StaleTransaction.into());
+        }
+        if transaction.approvals.count_ones() < multisig.threshold as u32 {
+            return Err(StrangemoodError::ThresholdNotReached.into());
+        }
+        transaction.is_executed = true;
+        // Persist that before the CPI, so a wrapped instruction that calls back
+        // into `execute_transaction` sees it already executed.
+        transaction.exit(&crate::ID)?;
+
+        // The wrapped instruction targets this program, with the multisig's
+        // signer PDA standing in as the listing or charter authority.
+        let signer = ctx.accounts.signer.key();
+        let instruction = Instruction {
+            program_id: crate::ID,
+            accounts: transaction
+                .accounts
+                .iter()
+                .map(|a| AccountMeta {
+                    pubkey: a.pubkey,
+                    is_signer: a.is_signer || a.pubkey == signer,
+                    is_writable: a.is_writable,
+                })
+                .collect(),
+            data: transaction.data.clone(),
+        };
+        let multisig_key = multisig.key();
+        invoke_signed(
+            &instruction,
+            ctx.remaining_accounts,
+            &[&[b"signer", multisig_key.as_ref(), &[signer_bump]]],
+        )?;
+        Ok(())
+    }
+
+    pub fn init_mock_price_feed(
+        ctx: Context<InitMockPriceFeed>,
+        price: i64,
+        conf: u64,
+        expo: i32,
+    ) -> Result<()> {
+        let feed = &mut ctx.accounts.price_feed;
+        feed.authority = ctx.accounts.authority.key();
+        feed.price = price;
+        feed.conf = conf;
+        feed.expo = expo;
+        feed.publish_time = Clock::get()?.unix_timestamp;
+
+        Ok(())
+    }
+
+    pub fn set_mock_price_feed(
+        ctx: Context<SetMockPriceFeed>,
+        price: i64,
+        conf: u64,
+        publish_time: i64,
+    ) -> Result<()> {
+        let feed = &mut ctx.accounts.price_feed;
+        feed.price = price;
+        feed.conf = conf;
+        feed.publish_time = publish_time;
+
+        Ok(())
+    }
+}
+
+// ─── Account Structs ────────────────────────────────────────────────────────
+
+#[derive(Accounts)]
+#[instruction(
+    receipt_nonce: u128,
+    receipt_bump: u8,
+    listing_mint_bump: u8,
+    escrow_authority_bump: u8,
+    amount: u64,
+    max_unit_price: u64,
+    voucher: Option<PurchaseVoucher>,
+)]
+pub struct Purchase<'info> {
+    #[account(mut)]
+    pub purchase_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = listing_mint.key() == listing.clone().into_inner().mint.key(),
+    )]
+    pub listing: Box<Account<'info, Listing>>,
+
+    #[account(has_one = listing)]
+    pub price_table: Option<Box<Account<'info, ListingPriceTable>>>,
+
+    /// CHECK: matched against the price table entry, parsed by its price feed adapter
+    pub price_feed: Option<AccountInfo<'info>>,
+
+    #[account(mut, has_one = listing)]
+    pub campaign: Option<Box<Account<'info, Campaign>>>,
+
+    #[account(
+        init,
+        seeds = [b"voucher", listing.key().as_ref(), &voucher.map(|v| v.nonce).unwrap_or_default().to_le_bytes()],
+        bump,
+        payer = user,
+        space = 8
+    )]
+    pub voucher_nonce: Option<Account<'info, ConsumedNonce>>,
+
+    /// CHECK: address constraint against the instructions sysvar
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: Option<AccountInfo<'info>>,
+
+    #[account(
+        constraint = listing_payment_deposit.mint == listing_payment_deposit_mint.key()
+    )]
+    pub listing_payment_deposit: Box<Account<'info, TokenAccount>>,
+
+    pub listing_payment_deposit_mint: Account<'info, Mint>,
+
+    /// CHECK: cashier is stored in receipt, validated during cash instruction
+    pub cashier: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub listing_token_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub listing_mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: PDA seed validation via seeds constraint
+    #[account(
+        seeds = [b"mint", listing_mint.key().as_ref()],
+        bump = listing_mint_bump,
+    )]
+    pub listing_mint_authority: AccountInfo<'info>,
+
+    #[account(
+        init,
+        seeds = [b"receipt" as &[u8], &receipt_nonce.to_le_bytes()],
+        bump,
+        payer = user,
+        space = 8 + 1 + 1 + 1 + 32 + 32 + 32 + 32 + 32 + 8 + 8 + 16 + 8 + 1 + 1
+    )]
+    pub receipt: Box<Account<'info, Receipt>>,
+
+    #[account(
+        init,
+        payer = user,
+        token::mint = listing_payment_deposit_mint,
+        token::authority = escrow_authority,
+    )]
+    pub escrow: Account<'info, TokenAccount>,
+
+    /// CHECK: PDA seed validation via seeds constraint
+    #[account(
+        seeds = [b"escrow", escrow.key().as_ref()],
+        bump = escrow_authority_bump,
+    )]
+    pub escrow_authority: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(listing_mint_bump: u8, charter_mint_bump: u8, escrow_authority_bump: u8)]
+pub struct Cash<'info> {
+    /// Pays for the listing's vesting escrow the first time it's cashed.
+    #[account(mut)]
+    pub cashier: Signer<'info>,
+
+    pub role: Option<Box<Account<'info, ListingRole>>>,
+
+    #[account(mut, has_one = listing, has_one = listing_token_account, has_one = escrow)]
+    pub receipt: Account<'info, Receipt>,
+
+    #[account(mut)]
+    pub escrow: Account<'info, TokenAccount>,
+
+    /// CHECK: PDA seed validation via seeds constraint
+    #[account(
+        seeds = [b"escrow", escrow.key().as_ref()],
+        bump = escrow_authority_bump,
+    )]
+    pub escrow_authority: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub listing_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub listings_payment_deposit: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        seeds = [b"vesting", listing.key().as_ref(), charter_mint.key().as_ref()],
+        bump,
+        payer = cashier,
+        space = 8 + 1 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 32
+    )]
+    pub vesting: Box<Account<'info, VoteVesting>>,
+
+    #[account(
+        init_if_needed,
+        seeds = [b"vault", vesting.key().as_ref()],
+        bump,
+        payer = cashier,
+        token::mint = charter_mint,
+        token::authority = vesting,
+    )]
+    pub vesting_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = charter.key() == listing.clone().into_inner().charter.key(),
+        constraint = listing_mint.key() == listing.clone().into_inner().mint.key(),
+    )]
+    pub listing: Box<Account<'info, Listing>>,
+
+    #[account(has_one = listing)]
+    pub price_table: Option<Box<Account<'info, ListingPriceTable>>>,
+
+    #[account(has_one = listing)]
+    pub campaign: Option<Box<Account<'info, Campaign>>>,
+
+    #[account(mut, has_one = listing)]
+    pub milestones: Option<Box<Account<'info, MilestoneVault>>>,
+
+    /// The purchaser's share of a milestone vault, for refunds if it's abandoned.
+    #[account(
+        init_if_needed,
+        seeds = [b"backing", listing.key().as_ref(), receipt.purchaser.as_ref()],
+        bump,
+        payer = cashier,
+        space = 8 + 1 + 32 + 32 + 8 + 8
+    )]
+    pub backing: Option<Box<Account<'info, MilestoneBacking>>>,
+
+    #[account(mut)]
+    pub listing_mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: PDA seed validation via seeds constraint
+    #[account(
+        seeds = [b"mint", listing_mint.key().as_ref()],
+        bump = listing_mint_bump,
+    )]
+    pub listing_mint_authority: AccountInfo<'info>,
+
+    #[account(
+        has_one = charter,
+        constraint = charter_treasury_deposit.key() == charter_treasury.clone().into_inner().deposit.key(),
+        constraint = charter_treasury.mint == listings_payment_deposit.mint,
+    )]
+    pub charter_treasury: Box<Account<'info, CharterTreasury>>,
+
+    #[account(mut)]
+    pub charter_treasury_deposit: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub charter_vote_deposit: Box<Account<'info, TokenAccount>>,
+
+    /// The purchaser's charter token account, for the buyer's share of votes.
+    #[account(mut, token::mint = charter_mint, token::authority = receipt.purchaser)]
+    pub purchaser_vote_deposit: Option<Box<Account<'info, TokenAccount>>>,
+
+    /// CHECK: the purchaser's role on the listing, which usually doesn't exist; read by `ListingRole::is_held`
+    #[account(seeds = [b"role", listing.key().as_ref(), receipt.purchaser.as_ref()], bump)]
+    pub purchaser_role: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub charter_mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: PDA seed validation via seeds constraint
+    #[account(
+        seeds = [b"mint", charter_mint.key().as_ref()],
+        bump = charter_mint_bump,
+    )]
+    pub charter_mint_authority: AccountInfo<'info>,
+
+    #[account(
+        constraint = charter.clone().into_inner().mint == charter_mint.key()
+    )]
+    pub charter: Box<Account<'info, Charter>>,
+
+    #[account(
+        init_if_needed,
+        seeds = [b"emissions", charter.key().as_ref()],
+        bump,
+        payer = cashier,
+        space = 8 + 1 + 32 + 8 + 8 + 8
+    )]
+    pub emissions: Box<Account<'info, CharterEmissions>>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(listing_bump: u8, listing_mint_authority_bump: u8)]
+pub struct Cancel<'info> {
+    pub purchaser: Signer<'info>,
+
+    #[account(mut)]
+    pub return_deposit: Account<'info, TokenAccount>,
+
+    #[account(mut, has_one = listing, has_one = listing_token_account, has_one = purchaser, has_one = escrow)]
+    pub receipt: Account<'info, Receipt>,
+
+    #[account(mut)]
+    pub escrow: Account<'info, TokenAccount>,
+
+    /// CHECK: PDA used as escrow authority
+    pub escrow_authority: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub listing_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, seeds = [b"listing", listing_mint.key().as_ref()], bump = listing_bump)]
+    pub listing: Box<Account<'info, Listing>>,
+
+    #[account(mut, has_one = listing)]
+    pub campaign: Option<Box<Account<'info, Campaign>>>,
+
+    pub listing_mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: PDA seed validation via seeds constraint
+    #[account(
+        seeds = [b"mint", listing_mint.key().as_ref()],
+        bump = listing_mint_authority_bump,
+    )]
+    pub listing_mint_authority: AccountInfo<'info>,
+
+    #[account(has_one = listing)]
+    pub price_table: Option<Box<Account<'info, ListingPriceTable>>>,
+
+    #[account(mut)]
+    pub listings_payment_deposit: Option<Box<Account<'info, TokenAccount>>>,
+
+    pub charter: Option<Box<Account<'info, Charter>>>,
+
+    pub charter_treasury: Option<Box<Account<'info, CharterTreasury>>>,
+
+    #[account(mut)]
+    pub charter_treasury_deposit: Option<Box<Account<'info, TokenAccount>>>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(listing_mint_bump: u8, escrow_authority_bump: u8)]
+pub struct Refund<'info> {
+    pub authority: Signer<'info>,
+
+    pub role: Option<Box<Account<'info, ListingRole>>>,
+
+    /// CHECK: receives the receipt and escrow rent, matched against the receipt
+    #[account(mut)]
+    pub purchaser: AccountInfo<'info>,
+
+    #[account(mut, token::authority = purchaser)]
+    pub return_deposit: Account<'info, TokenAccount>,
+
+    #[account(mut, has_one = listing, has_one = listing_token_account, has_one = purchaser, has_one = escrow)]
+    pub receipt: Account<'info, Receipt>,
+
+    #[account(mut)]
+    pub escrow: Account<'info, TokenAccount>,
+
+    /// CHECK: PDA seed validation via seeds constraint
+    #[account(
+        seeds = [b"escrow", escrow.key().as_ref()],
+        bump = escrow_authority_bump,
+    )]
+    pub escrow_authority: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub listing_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, has_one = charter)]
+    pub listing: Box<Account<'info, Listing>>,
+
+    pub charter: Box<Account<'info, Charter>>,
+
+    #[account(mut, has_one = listing)]
+    pub campaign: Option<Box<Account<'info, Campaign>>>,
+
+    #[account(mut, address = listing.mint @ StrangemoodError::UnexpectedListingMint)]
+    pub listing_mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: PDA seed validation via seeds constraint
+    #[account(
+        seeds = [b"mint", listing_mint.key().as_ref()],
+        bump = listing_mint_bump,
+    )]
+    pub listing_mint_authority: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(dispute_bump: u8)]
+pub struct OpenDispute<'info> {
+    #[account(
+        init,
+        seeds = [b"dispute", receipt.key().as_ref()],
+        bump,
+        payer = purchaser,
+        space = 8 + 1 + 32 + 32 + 32 + 8 + 8 + 4 + 128
+    )]
+    pub dispute: Account<'info, Dispute>,
+
+    #[account(mut, has_one = purchaser, has_one = listing)]
+    pub receipt: Account<'info, Receipt>,
+
+    #[account(mut, has_one = charter)]
+    pub listing: Box<Account<'info, Listing>>,
+
+    pub charter: Box<Account<'info, Charter>>,
+
+    #[account(mut)]
+    pub purchaser: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(listing_mint_bump: u8, escrow_authority_bump: u8)]
+pub struct ResolveDispute<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(mut, has_one = receipt, has_one = charter, has_one = purchaser, close = purchaser)]
+    pub dispute: Account<'info, Dispute>,
+
+    /// CHECK: receives the dispute, receipt and escrow rent, matched against the dispute
+    #[account(mut)]
+    pub purchaser: AccountInfo<'info>,
+
+    #[account(mut, token::authority = purchaser)]
+    pub return_deposit: Account<'info, TokenAccount>,
+
+    #[account(mut, has_one = listing, has_one = listing_token_account, has_one = escrow)]
+    pub receipt: Account<'info, Receipt>,
+
+    #[account(mut)]
+    pub escrow: Account<'info, TokenAccount>,
+
+    /// CHECK: PDA seed validation via seeds constraint
+    #[account(
+        seeds = [b"escrow", escrow.key().as_ref()],
+        bump = escrow_authority_bump,
+    )]
+    pub escrow_authority: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub listing_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, has_one = charter)]
+    pub listing: Box<Account<'info, Listing>>,
+
+    pub charter: Box<Account<'info, Charter>>,
+
+    #[account(mut, has_one = listing)]
+    pub campaign: Option<Box<Account<'info, Campaign>>>,
+
+    #[account(mut, address = listing.mint @ StrangemoodError::UnexpectedListingMint)]
+    pub listing_mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: PDA seed validation via seeds constraint
+    #[account(
+        seeds = [b"mint", listing_mint.key().as_ref()],
+        bump = listing_mint_bump,
+    )]
+    pub listing_mint_authority: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ResolveDispute<'info> {
+    /// Releases the receipt to the cashier, or unwinds it back to the purchaser.
+    fn execute(
+        &mut self,
+        ruling: DisputeRuling,
+        listing_mint_bump: u8,
+        escrow_authority_bump: u8,
+    ) -> Result<()> {
+        match ruling {
+            DisputeRuling::Release => {
+                self.listing.open_disputes -= 1;
+                self.receipt.is_disputed = false;
+                self.receipt.is_cashable = true;
+                Ok(())
+            }
+            DisputeRuling::Refund => {
+                if self.listing.is_campaign {
+                    let campaign = match self.campaign.as_deref_mut() {
+                        Some(campaign) => campaign,
+                        None => return Err(StrangemoodError::CampaignRequired.into()),
+                    };
+                    campaign.withdraw(self.escrow.amount, true)?;
+                }
+
+                if self.receipt.is_refundable {
+                    thaw_and_burn(
+                        self.token_program.to_account_info(),
+                        self.listing_mint.to_account_info(),
+                        &self.listing_token_account,
+                        self.listing_mint_authority.to_account_info(),
+                        listing_mint_bump,
+                        self.receipt.quantity,
+                    )?;
+                }
+
+                self.listing.open_disputes -= 1;
+                self.listing.open_receipts -= 1;
+                close_native_account(
+                    &self.receipt.to_account_info(),
+                    &self.purchaser.to_account_info(),
+                );
+
+                token_escrow_transfer(
+                    self.token_program.to_account_info(),
+                    self.escrow.to_account_info(),
+                    self.return_deposit.to_account_info(),
+                    self.escrow_authority.to_account_info(),
+                    self.escrow.amount,
+                    escrow_authority_bump,
+                )?;
+
+                close_token_escrow_account(
+                    self.token_program.to_account_info(),
+                    self.escrow.to_account_info(),
+                    self.purchaser.to_account_info(),
+                    self.escrow_authority.to_account_info(),
+                    escrow_authority_bump,
+                )
+            }
+        }
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(listing_bump: u8, listing_mint_authority_bump: u8, amount: u64, attestation_nonce: Option<u64>)]
+pub struct Consume<'info> {
+    #[account(seeds = [b"listing", mint.key().as_ref()], bump = listing_bump, has_one = mint)]
+    pub listing: Box<Account<'info, Listing>>,
+
+    pub mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: PDA seed validation via seeds constraint
+    #[account(
+        seeds = [b"mint", mint.key().as_ref()],
+        bump = listing_mint_authority_bump,
+    )]
+    pub mint_authority: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub listing_token_account: Box<Account<'info, TokenAccount>>,
+    pub token_program: Program<'info, Token>,
+
+    #[account(mut)]
+    pub session: Option<Box<Account<'info, Session>>>,
+
+    pub role: Option<Box<Account<'info, ListingRole>>>,
+
+    #[account(
+        init,
+        seeds = [
+            b"nonce",
+            listing.key().as_ref(),
+            listing_token_account.owner.as_ref(),
+            &attestation_nonce.unwrap_or_default().to_le_bytes(),
+        ],
+        bump,
+        payer = authority,
+        space = 8
+    )]
+    pub consumed_nonce: Option<Account<'info, ConsumedNonce>>,
+
+    /// CHECK: address constraint against the instructions sysvar
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: Option<AccountInfo<'info>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(session_bump: u8)]
+pub struct CreateSession<'info> {
+    #[account(
+        init,
+        seeds = [b"session", listing_token_account.key().as_ref(), session_key.key().as_ref()],
+        bump,
+        payer = holder,
+        space = 8 + 1 + 32 + 32 + 32 + 1 + 8 + 8 + 8
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(token::authority = holder)]
+    pub listing_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: ephemeral key authorized by the session, no validation needed
+    pub session_key: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub holder: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeSession<'info> {
+    #[account(mut, has_one = holder, close = holder)]
+    pub session: Account<'info, Session>,
+
+    #[account(mut)]
+    pub holder: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(code_batch_bump: u8, batch_id: u64, root: [u8; 32], size: u32)]
+pub struct InitCodeBatch<'info> {
+    #[account(
+        init,
+        seeds = [b"codes", listing.key().as_ref(), &batch_id.to_le_bytes()],
+        bump,
+        payer = user,
+        space = 8 + 1 + 32 + 8 + 32 + 4 + 4 + 4 + (size.min(MAX_CODE_BATCH_SIZE) as usize).div_ceil(8)
+    )]
+    pub code_batch: Account<'info, CodeBatch>,
+
+    pub listing: Box<Account<'info, Listing>>,
+
+    #[account(address = listing.charter)]
+    pub charter: Box<Account<'info, Charter>>,
+
+    #[account(has_one = charter)]
+    pub charter_treasury: Option<Box<Account<'info, CharterTreasury>>>,
+
+    #[account(mut)]
+    pub charter_treasury_deposit: Option<Box<Account<'info, TokenAccount>>>,
+
+    #[account(address = listing.payment_deposit)]
+    pub listing_payment_deposit: Option<Box<Account<'info, TokenAccount>>>,
+
+    #[account(mut)]
+    pub fee_token_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(listing_mint_bump: u8)]
+pub struct RedeemCode<'info> {
+    #[account(mut, has_one = listing)]
+    pub code_batch: Account<'info, CodeBatch>,
+
+    pub listing: Box<Account<'info, Listing>>,
+
+    #[account(mut, address = listing.mint @ StrangemoodError::UnexpectedListingMint)]
+    pub listing_mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: PDA seed validation via seeds constraint
+    #[account(
+        seeds = [b"mint", listing_mint.key().as_ref()],
+        bump = listing_mint_bump,
+    )]
+    pub listing_mint_authority: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        token::mint = listing_mint,
+        token::authority = redeemer,
+    )]
+    pub listing_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: address constraint against the instructions sysvar
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: AccountInfo<'info>,
+
+    pub redeemer: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(distributor_bump: u8, distributor_id: u64, root: [u8; 32], max_claims: u32)]
+pub struct InitDistributor<'info> {
+    #[account(
+        init,
+        seeds = [b"distributor", listing.key().as_ref(), &distributor_id.to_le_bytes()],
+        bump,
+        payer = user,
+        space = 8 + 1 + 32 + 8 + 32 + 4 + 4 + 8 + 4 + (max_claims.min(MAX_DISTRIBUTOR_CLAIMS) as usize).div_ceil(8)
+    )]
+    pub distributor: Account<'info, Distributor>,
+
+    pub listing: Box<Account<'info, Listing>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(listing_mint_bump: u8)]
+pub struct ClaimLicense<'info> {
+    #[account(mut, has_one = listing)]
+    pub distributor: Account<'info, Distributor>,
+
+    pub listing: Box<Account<'info, Listing>>,
+
+    #[account(mut, address = listing.mint @ StrangemoodError::UnexpectedListingMint)]
+    pub listing_mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: PDA seed validation via seeds constraint
+    #[account(
+        seeds = [b"mint", listing_mint.key().as_ref()],
+        bump = listing_mint_bump,
+    )]
+    pub listing_mint_authority: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        token::mint = listing_mint,
+        token::authority = claimant,
+    )]
+    pub listing_token_account: Box<Account<'info, TokenAccount>>,
+
+    pub claimant: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(revocation_bump: u8, listing_mint_bump: u8)]
+pub struct RevokeLicense<'info> {
+    #[account(
+        init_if_needed,
+        seeds = [b"revocation", listing_token_account.key().as_ref()],
+        bump,
+        payer = authority,
+        space = 8 + 1 + 32 + 32 + 1 + 8 + 32 + 4 + 128 + 8
+    )]
+    pub revocation: Account<'info, LicenseRevocation>,
+
+    #[account(mut, token::mint = listing_mint)]
+    pub listing_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(has_one = charter)]
+    pub listing: Box<Account<'info, Listing>>,
+
+    pub charter: Box<Account<'info, Charter>>,
+
+    #[account(address = listing.mint @ StrangemoodError::UnexpectedListingMint)]
+    pub listing_mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: PDA seed validation via seeds constraint
+    #[account(
+        seeds = [b"mint", listing_mint.key().as_ref()],
+        bump = listing_mint_bump,
+    )]
+    pub listing_mint_authority: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReinstateLicense<'info> {
+    #[account(mut, has_one = listing)]
+    pub revocation: Account<'info, LicenseRevocation>,
+
+    #[account(has_one = charter)]
+    pub listing: Box<Account<'info, Listing>>,
+
+    pub charter: Box<Account<'info, Charter>>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(revocation_bump: u8)]
+pub struct SetLicenseExpiry<'info> {
+    #[account(
+        init_if_needed,
+        seeds = [b"revocation", listing_token_account.key().as_ref()],
+        bump,
+        payer = authority,
+        space = 8 + 1 + 32 + 32 + 1 + 8 + 32 + 4 + 128 + 8
+    )]
+    pub revocation: Account<'info, LicenseRevocation>,
+
+    #[account(constraint = listing_token_account.mint == listing.mint @ StrangemoodError::UnexpectedListingMint)]
+    pub listing_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(has_one = charter)]
+    pub listing: Box<Account<'info, Listing>>,
+
+    pub charter: Box<Account<'info, Charter>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyLicense<'info> {
+    pub listing: Box<Account<'info, Listing>>,
+
+    pub listing_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: the wallet being asked about, compared against the token account owner
+    pub holder: AccountInfo<'info>,
+
+    /// CHECK: PDA seed validation via seeds constraint; empty if never revoked
+    #[account(
+        seeds = [b"revocation", listing_token_account.key().as_ref()],
+        bump,
+    )]
+    pub revocation: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetReceiptCashable<'info> {
+    pub listing: Box<Account<'info, Listing>>,
+
+    pub role: Option<Account<'info, ListingRole>>,
+
+    #[account(mut, has_one = listing)]
+    pub receipt: Account<'info, Receipt>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint_bump: u8, listing_bump: u8, listing_mint_decimals: u8)]
+pub struct InitListing<'info> {
+    #[account(
+        init,
+        seeds = [b"listing", mint.key().as_ref()],
+        bump,
+        payer = user,
+        space = 8 + 235 + 128 + 1 + 1 + 2 + 8 + 1 + 32 + 32 + 32 + 1 + 8 + 8 + 8
+    )]
+    pub listing: Box<Account<'info, Listing>>,
+
+    /// CHECK: PDA seed validation via seeds constraint
+    #[account(
+        seeds = [b"mint", mint.key().as_ref()],
+        bump = mint_bump,
+    )]
+    pub mint_authority_pda: AccountInfo<'info>,
+
+    #[account(
+        init,
+        mint::decimals = listing_mint_decimals,
+        mint::authority = mint_authority_pda,
+        mint::freeze_authority = mint_authority_pda,
+        payer = user
+    )]
+    pub mint: Box<Account<'info, Mint>>,
+
+    pub payment_deposit: Box<Account<'info, TokenAccount>>,
+    pub vote_deposit: Box<Account<'info, TokenAccount>>,
+
+    pub charter: Box<Account<'info, Charter>>,
+
+    #[account(has_one = charter)]
+    pub charter_treasury: Box<Account<'info, CharterTreasury>>,
+
+    pub rent: Sysvar<'info, Rent>,
+    pub token_program: Program<'info, Token>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetListing<'info> {
+    #[account(mut)]
+    pub listing: Account<'info, Listing>,
+
+    pub role: Option<Account<'info, ListingRole>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> SetListing<'info> {
+    /// Allows the listing authority, or a holder of `roles`.
+    pub fn authorize(&self, roles: u8) -> Result<()> {
+        let user = self.user.key();
+        if user != self.listing.authority
+            && !ListingRole::grants(
+                self.role.as_deref(),
+                &self.listing.key(),
+                self.listing.role_version,
+                &user,
+                roles,
+            )
+        {
+            return Err(StrangemoodError::UnauthorizedAuthority.into());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct SetListingDeposit<'info> {
+    #[account(mut)]
+    pub listing: Account<'info, Listing>,
+
+    pub payment_deposit: Account<'info, TokenAccount>,
+    pub vote_deposit: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetListingAuthority<'info> {
+    #[account(mut)]
+    pub listing: Account<'info, Listing>,
+
+    /// CHECK: new authority, no validation needed
+    pub authority: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptListingAuthority<'info> {
+    #[account(mut)]
+    pub listing: Account<'info, Listing>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelListingAuthority<'info> {
+    #[account(mut)]
+    pub listing: Account<'info, Listing>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(role_bump: u8)]
+pub struct GrantListingRole<'info> {
+    #[account(
+        init_if_needed,
+        seeds = [b"role", listing.key().as_ref(), grantee.key().as_ref()],
+        bump,
+        payer = user,
+        space = 8 + 1 + 32 + 32 + 1 + 8
+    )]
+    pub role: Account<'info, ListingRole>,
+
+    pub listing: Account<'info, Listing>,
+
+    /// CHECK: key receiving the roles, no validation needed
+    pub grantee: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeListingRole<'info> {
+    #[account(mut, has_one = listing)]
+    pub role: Account<'info, ListingRole>,
+
+    pub listing: Account<'info, Listing>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetListingPricingKey<'info> {
+    #[account(mut)]
+    pub listing: Account<'info, Listing>,
+
+    /// CHECK: storefront signing key, no validation needed
+    pub pricing_key: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetListingAttestationKey<'info> {
+    #[account(mut)]
+    pub listing: Account<'info, Listing>,
+
+    /// CHECK: game server signing key, no validation needed
+    pub attestation_key: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetListingCharter<'info> {
+    #[account(mut)]
+    pub listing: Account<'info, Listing>,
+
+    pub charter: Account<'info, Charter>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(price_table_bump: u8)]
+pub struct InitListingPriceTable<'info> {
+    #[account(
+        init,
+        seeds = [b"prices", listing.key().as_ref()],
+        bump,
+        payer = user,
+        space = 8 + 1 + 32 + 4 + MAX_PRICE_TABLE_ENTRIES * (32 + 8 + 32 + 1 + 1 + 32 + 4 + 2)
+    )]
+    pub price_table: Account<'info, ListingPriceTable>,
+
+    #[account(mut)]
+    pub listing: Account<'info, Listing>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetListingPriceTableEntry<'info> {
+    #[account(mut, has_one = listing)]
+    pub price_table: Account<'info, ListingPriceTable>,
+
+    #[account(has_one = charter)]
+    pub listing: Account<'info, Listing>,
+
+    pub charter: Account<'info, Charter>,
+
+    #[account(
+        has_one = charter,
+        constraint = charter_treasury.mint == payment_deposit.mint @ StrangemoodError::MintNotSupported,
+    )]
+    pub charter_treasury: Account<'info, CharterTreasury>,
+
+    pub payment_deposit: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveListingPriceTableEntry<'info> {
+    #[account(mut, has_one = listing)]
+    pub price_table: Account<'info, ListingPriceTable>,
+
+    pub listing: Account<'info, Listing>,
+
+    pub mint: Account<'info, Mint>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(campaign_bump: u8)]
+pub struct InitCampaign<'info> {
+    #[account(
+        init,
+        seeds = [b"campaign", listing.key().as_ref()],
+        bump,
+        payer = user,
+        space = 8 + 1 + 32 + 32 + 8 + 8 + 8 + 8 + 1
+    )]
+    pub campaign: Account<'info, Campaign>,
+
+    #[account(mut)]
+    pub listing: Account<'info, Listing>,
+
+    pub mint: Account<'info, Mint>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(milestones_bump: u8)]
+pub struct InitMilestones<'info> {
+    #[account(
+        init,
+        seeds = [b"milestones", listing.key().as_ref()],
+        bump,
+        payer = user,
+        space = 8 + 1 + 32 + 32 + 32 + 4 + MAX_MILESTONES * (2 + 1) + 8 + 8 + 8 + 1 + 8 + 8
+    )]
+    pub milestones: Box<Account<'info, MilestoneVault>>,
+
+    #[account(
+        init,
+        seeds = [b"milestone_vault", listing.key().as_ref()],
+        bump,
+        payer = user,
+        token::mint = mint,
+        token::authority = milestones,
+    )]
+    pub vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, constraint = !listing.is_milestone_funded @ StrangemoodError::MilestonesLocked)]
+    pub listing: Box<Account<'info, Listing>>,
+
+    #[account(
+        has_one = mint,
+        constraint = payment_deposit.key() == listing.payment_deposit @ StrangemoodError::DepositIsNotFoundInListing,
+    )]
+    pub payment_deposit: Box<Account<'info, TokenAccount>>,
+
+    pub mint: Box<Account<'info, Mint>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub rent: Sysvar<'info, Rent>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(milestones_bump: u8)]
+pub struct ApproveMilestone<'info> {
+    #[account(
+        mut,
+        seeds = [b"milestones", listing.key().as_ref()],
+        bump = milestones_bump,
+        has_one = listing,
+        has_one = vault,
+        has_one = beneficiary,
+    )]
+    pub milestones: Box<Account<'info, MilestoneVault>>,
+
+    #[account(mut)]
+    pub vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub beneficiary: Box<Account<'info, TokenAccount>>,
+
+    #[account(has_one = charter)]
+    pub listing: Box<Account<'info, Listing>>,
+
+    pub charter: Box<Account<'info, Charter>>,
+
+    /// A queued `ApproveMilestone`, consumed by the approval.
+    #[account(mut, has_one = charter, has_one = payer, close = payer)]
+    pub pending: Option<Box<Account<'info, PendingCharterChange>>>,
+
+    /// CHECK: receives the pending change's rent, checked by has_one
+    #[account(mut)]
+    pub payer: Option<AccountInfo<'info>>,
+
+    pub user: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(milestones_bump: u8)]
+pub struct ReleaseMilestoneFunds<'info> {
+    #[account(
+        mut,
+        seeds = [b"milestones", milestones.listing.as_ref()],
+        bump = milestones_bump,
+        has_one = vault,
+        has_one = beneficiary,
+    )]
+    pub milestones: Box<Account<'info, MilestoneVault>>,
+
+    #[account(mut)]
+    pub vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub beneficiary: Box<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct AbandonMilestones<'info> {
+    #[account(mut, has_one = listing, has_one = vault)]
+    pub milestones: Box<Account<'info, MilestoneVault>>,
+
+    pub vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, has_one = charter)]
+    pub listing: Box<Account<'info, Listing>>,
+
+    pub charter: Box<Account<'info, Charter>>,
+
+    /// A queued `AbandonMilestones`, consumed by the abandonment.
+    #[account(mut, has_one = charter, has_one = payer, close = payer)]
+    pub pending: Option<Box<Account<'info, PendingCharterChange>>>,
+
+    /// CHECK: receives the pending change's rent, checked by has_one
+    #[account(mut)]
+    pub payer: Option<AccountInfo<'info>>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(milestones_bump: u8, listing_mint_bump: u8)]
+pub struct ClaimMilestoneRefund<'info> {
+    #[account(
+        mut,
+        seeds = [b"milestones", listing.key().as_ref()],
+        bump = milestones_bump,
+        has_one = listing,
+        has_one = vault,
+    )]
+    pub milestones: Box<Account<'info, MilestoneVault>>,
+
+    #[account(
+        mut,
+        has_one = milestones,
+        constraint = backing.purchaser == holder.key() @ StrangemoodError::UnauthorizedAuthority,
+    )]
+    pub backing: Box<Account<'info, MilestoneBacking>>,
+
+    #[account(mut)]
+    pub vault: Box<Account<'info, TokenAccount>>,
+
+    pub listing: Box<Account<'info, Listing>>,
+
+    #[account(mut, address = listing.mint @ StrangemoodError::UnexpectedListingMint)]
+    pub listing_mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: PDA seed validation via seeds constraint
+    #[account(
+        seeds = [b"mint", listing_mint.key().as_ref()],
+        bump = listing_mint_bump,
+    )]
+    pub listing_mint_authority: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        token::mint = listing_mint,
+        token::authority = holder,
+    )]
+    pub listing_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, token::mint = vault.mint)]
+    pub return_deposit: Box<Account<'info, TokenAccount>>,
+
+    pub holder: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct InitMockPriceFeed<'info> {
+    #[account(init, payer = authority, space = 8 + 32 + 8 + 8 + 4 + 8)]
+    pub price_feed: Account<'info, MockPriceFeed>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetMockPriceFeed<'info> {
+    #[account(mut, has_one = authority)]
+    pub price_feed: Account<'info, MockPriceFeed>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(charter_bump: u8)]
+pub struct InitCharter<'info> {
+    #[account(
+        init,
+        seeds = [b"charter", mint.key().as_ref()],
+        bump,
+        payer = user,
+        space = 8 + 8 + 1 + 8 + 1 + 8 + 1 + 32 + 32 + 32 + 128 + 256 + 32 + 8 + 1 + 1 + 8 + 8 + 2 + 2 + 8 + 32 + 8 + 8 + 2 + 8 + 8 + 8 + 8 + 8 + 1 + 2
+    )]
+    pub charter: Account<'info, Charter>,
+
+    pub mint: Account<'info, Mint>,
+
+    /// CHECK: authority stored in charter, validated in set instructions
+    pub authority: AccountInfo<'info>,
+
+    pub vote_deposit: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetCharter<'info> {
+    #[account(mut)]
+    pub charter: Account<'info, Charter>,
+
+    #[account(
+        init,
+        seeds = [b"pending", charter.key().as_ref(), &charter.change_count.to_le_bytes()],
+        bump,
+        payer = user,
+        space = 8 + 1 + 32 + 8 + CharterChange::SPACE + 32 + 8 + 8
+    )]
+    pub pending: Account<'info, PendingCharterChange>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetCharterArbiter<'info> {
+    #[account(mut)]
+    pub charter: Account<'info, Charter>,
+
+    #[account(
+        init,
+        seeds = [b"pending", charter.key().as_ref(), &charter.change_count.to_le_bytes()],
+        bump,
+        payer = user,
+        space = 8 + 1 + 32 + 8 + CharterChange::SPACE + 32 + 8 + 8
+    )]
+    pub pending: Account<'info, PendingCharterChange>,
+
+    /// CHECK: new arbiter, no validation needed
+    pub arbiter: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetCharterVoteDeposit<'info> {
+    #[account(mut)]
+    pub charter: Account<'info, Charter>,
+
+    #[account(
+        init,
+        seeds = [b"pending", charter.key().as_ref(), &charter.change_count.to_le_bytes()],
+        bump,
+        payer = user,
+        space = 8 + 1 + 32 + 8 + CharterChange::SPACE + 32 + 8 + 8
+    )]
+    pub pending: Account<'info, PendingCharterChange>,
+
+    pub vote_deposit: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetCharterAuthority<'info> {
+    #[account(mut)]
+    pub charter: Account<'info, Charter>,
+
+    #[account(
+        init,
+        seeds = [b"pending", charter.key().as_ref(), &charter.change_count.to_le_bytes()],
+        bump,
+        payer = user,
+        space = 8 + 1 + 32 + 8 + CharterChange::SPACE + 32 + 8 + 8
+    )]
+    pub pending: Account<'info, PendingCharterChange>,
+
+    /// CHECK: new authority, no validation needed
+    pub authority: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApplyCharterChange<'info> {
+    #[account(mut, has_one = charter, has_one = payer, close = payer)]
+    pub pending: Account<'info, PendingCharterChange>,
+
+    #[account(mut)]
+    pub charter: Account<'info, Charter>,
+
+    #[account(mut, has_one = charter)]
+    pub treasury: Option<Box<Account<'info, CharterTreasury>>>,
+
+    #[account(mut, has_one = charter)]
+    pub governance: Option<Box<Account<'info, Governance>>>,
+
+    /// CHECK: receives the pending change's rent, checked by has_one
+    #[account(mut)]
+    pub payer: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptCharterAuthority<'info> {
+    #[account(mut)]
+    pub charter: Account<'info, Charter>,
+
+    #[account(has_one = charter)]
+    pub governance: Option<Account<'info, Governance>>,
+
+    pub authority: Option<Signer<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct CancelCharterAuthority<'info> {
+    #[account(mut)]
+    pub charter: Account<'info, Charter>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetCharterCaps<'info> {
+    #[account(mut)]
+    pub charter: Account<'info, Charter>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelCharterChange<'info> {
+    #[account(mut, has_one = charter, has_one = payer, close = payer)]
+    pub pending: Account<'info, PendingCharterChange>,
+
+    pub charter: Account<'info, Charter>,
+
+    /// CHECK: receives the pending change's rent, checked by has_one
+    #[account(mut)]
+    pub payer: AccountInfo<'info>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(treasury_bump: u8)]
+pub struct InitCharterTreasury<'info> {
+    #[account(
+        init,
+        seeds = [b"treasury", charter.key().as_ref(), mint.key().as_ref()],
+        bump,
+        payer = authority,
+        space = 8 + 1 + 32 + 32 + 8 + 1 + 8 + 8 + 8 + 8 + 8 + 128
+    )]
+    pub treasury: Account<'info, CharterTreasury>,
+
+    #[account(has_one = authority)]
+    pub charter: Account<'info, Charter>,
+
+    #[account(
+        init,
+        seeds = [b"vault", treasury.key().as_ref()],
+        bump,
+        payer = authority,
+        token::mint = mint,
+        token::authority = treasury,
+    )]
+    pub deposit: Account<'info, TokenAccount>,
+
+    pub mint: Account<'info, Mint>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct SetCharterTreasuryExpansionScalar<'info> {
+    #[account(has_one = charter)]
+    pub treasury: Account<'info, CharterTreasury>,
+
+    #[account(mut, has_one = authority)]
+    pub charter: Account<'info, Charter>,
+
+    #[account(
+        init,
+        seeds = [b"pending", charter.key().as_ref(), &charter.change_count.to_le_bytes()],
+        bump,
+        payer = authority,
+        space = 8 + 1 + 32 + 8 + CharterChange::SPACE + 32 + 8 + 8
+    )]
+    pub pending: Account<'info, PendingCharterChange>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetCharterTreasuryDeposit<'info> {
+    #[account(has_one = charter, has_one = mint)]
+    pub treasury: Account<'info, CharterTreasury>,
+
+    #[account(mut, has_one = authority)]
+    pub charter: Account<'info, Charter>,
 
     #[account(
         init,
-        seeds = [b"receipt" as &[u8], &receipt_nonce.to_le_bytes()],
+        seeds = [b"pending", charter.key().as_ref(), &charter.change_count.to_le_bytes()],
         bump,
-        payer = user,
-        space = 8 + 1 + 1 + 1 + 32 + 32 + 32 + 32 + 32 + 8 + 8 + 16
+        payer = authority,
+        space = 8 + 1 + 32 + 8 + CharterChange::SPACE + 32 + 8 + 8
     )]
-    pub receipt: Box<Account<'info, Receipt>>,
+    pub pending: Account<'info, PendingCharterChange>,
+
+    /// Only vaults the treasury itself controls can hold charter contributions.
+    #[account(has_one = mint, token::authority = treasury)]
+    pub deposit: Account<'info, TokenAccount>,
+    pub mint: Account<'info, Mint>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetCharterTreasuryLimit<'info> {
+    #[account(has_one = charter)]
+    pub treasury: Account<'info, CharterTreasury>,
+
+    #[account(mut, has_one = authority)]
+    pub charter: Account<'info, Charter>,
 
     #[account(
         init,
-        payer = user,
-        token::mint = listing_payment_deposit_mint,
-        token::authority = escrow_authority,
+        seeds = [b"pending", charter.key().as_ref(), &charter.change_count.to_le_bytes()],
+        bump,
+        payer = authority,
+        space = 8 + 1 + 32 + 8 + CharterChange::SPACE + 32 + 8 + 8
     )]
-    pub escrow: Account<'info, TokenAccount>,
+    pub pending: Account<'info, PendingCharterChange>,
 
-    /// CHECK: PDA seed validation via seeds constraint
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(treasury_bump: u8)]
+pub struct WithdrawFromTreasury<'info> {
     #[account(
-        seeds = [b"escrow", escrow.key().as_ref()],
-        bump = escrow_authority_bump,
+        mut,
+        seeds = [b"treasury", charter.key().as_ref(), treasury.mint.as_ref()],
+        bump = treasury_bump,
+        has_one = charter,
     )]
-    pub escrow_authority: AccountInfo<'info>,
+    pub treasury: Account<'info, CharterTreasury>,
+
+    pub charter: Account<'info, Charter>,
+
+    /// Any vault owned by the treasury, including ones it has since moved away from.
+    #[account(mut, token::mint = treasury.mint, token::authority = treasury)]
+    pub deposit: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub destination: Account<'info, TokenAccount>,
+
+    /// A queued `TreasuryWithdrawal`, consumed by the withdrawal.
+    #[account(mut, has_one = charter, has_one = payer, close = payer)]
+    pub pending: Option<Box<Account<'info, PendingCharterChange>>>,
+
+    /// CHECK: receives the pending change's rent, checked by has_one
+    #[account(mut)]
+    pub payer: Option<AccountInfo<'info>>,
 
     #[account(mut)]
     pub user: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(vesting_bump: u8)]
+pub struct ClaimVestedVotes<'info> {
+    #[account(
+        mut,
+        seeds = [b"vesting", listing.key().as_ref(), vesting.mint.as_ref()],
+        bump = vesting_bump,
+        has_one = listing,
+        has_one = vault,
+    )]
+    pub vesting: Account<'info, VoteVesting>,
+
+    #[account(mut)]
+    pub vault: Account<'info, TokenAccount>,
+
+    pub listing: Account<'info, Listing>,
+
+    #[account(mut, address = listing.vote_deposit @ StrangemoodError::DepositIsNotFoundInListing)]
+    pub listing_vote_deposit: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(governance_bump: u8)]
+pub struct InitGovernance<'info> {
+    #[account(
+        init,
+        seeds = [b"governance", charter.key().as_ref()],
+        bump,
+        payer = authority,
+        space = 8 + 1 + 32 + 32 + 8 + 2 + 8 + 8
+    )]
+    pub governance: Account<'info, Governance>,
+
+    #[account(
+        init,
+        seeds = [b"vault", governance.key().as_ref()],
+        bump,
+        payer = authority,
+        token::mint = charter_mint,
+        token::authority = governance,
+    )]
+    pub vault: Account<'info, TokenAccount>,
+
+    #[account(has_one = authority, constraint = charter.mint == charter_mint.key() @ StrangemoodError::MintNotSupported)]
+    pub charter: Account<'info, Charter>,
+    pub charter_mint: Account<'info, Mint>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
     pub system_program: Program<'info, System>,
     pub token_program: Program<'info, Token>,
     pub rent: Sysvar<'info, Rent>,
 }
 
 #[derive(Accounts)]
-#[instruction(listing_mint_bump: u8, charter_mint_bump: u8, escrow_authority_bump: u8)]
-pub struct Cash<'info> {
-    pub cashier: Signer<'info>,
+#[instruction(proposal_bump: u8)]
+pub struct CreateProposal<'info> {
+    #[account(
+        init,
+        seeds = [b"proposal", governance.key().as_ref(), &governance.proposal_count.to_le_bytes()],
+        bump,
+        payer = proposer,
+        space = 8 + 1 + 32 + 32 + 8 + CharterChange::SPACE + 4 + 128 + 8 + 8 + 8 + 8 + 1
+    )]
+    pub proposal: Account<'info, Proposal>,
 
-    #[account(mut, has_one = listing, has_one = listing_token_account, has_one = cashier, has_one = escrow)]
-    pub receipt: Account<'info, Receipt>,
+    #[account(mut)]
+    pub governance: Account<'info, Governance>,
 
     #[account(mut)]
-    pub escrow: Account<'info, TokenAccount>,
+    pub proposer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
 
-    /// CHECK: PDA seed validation via seeds constraint
+#[derive(Accounts)]
+#[instruction(vote_bump: u8)]
+pub struct CastVote<'info> {
     #[account(
-        seeds = [b"escrow", escrow.key().as_ref()],
-        bump = escrow_authority_bump,
+        init,
+        seeds = [b"vote", proposal.key().as_ref(), voter.key().as_ref()],
+        bump,
+        payer = voter,
+        space = 8 + 1 + 32 + 32 + 8 + 1
     )]
-    pub escrow_authority: AccountInfo<'info>,
+    pub vote: Account<'info, VoteRecord>,
+
+    #[account(mut, has_one = governance)]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(has_one = vault)]
+    pub governance: Account<'info, Governance>,
 
     #[account(mut)]
-    pub listing_token_account: Box<Account<'info, TokenAccount>>,
+    pub vault: Account<'info, TokenAccount>,
 
     #[account(mut)]
-    pub listings_payment_deposit: Box<Account<'info, TokenAccount>>,
+    pub voter_token_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub voter: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(governance_bump: u8)]
+pub struct WithdrawVote<'info> {
+    #[account(mut, has_one = proposal, has_one = voter, close = voter)]
+    pub vote: Account<'info, VoteRecord>,
+
+    #[account(has_one = governance)]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(has_one = vault)]
+    pub governance: Account<'info, Governance>,
 
     #[account(mut)]
-    pub listings_vote_deposit: Box<Account<'info, TokenAccount>>,
+    pub vault: Account<'info, TokenAccount>,
 
-    #[account(
-        constraint = charter.key() == listing.clone().into_inner().charter.key(),
-        constraint = listing_mint.key() == listing.clone().into_inner().mint.key(),
-        constraint = listings_payment_deposit.key() == listing.clone().into_inner().payment_deposit.key(),
-        constraint = listings_vote_deposit.key() == listing.clone().into_inner().vote_deposit.key(),
-    )]
-    pub listing: Box<Account<'info, Listing>>,
+    #[account(mut, token::authority = voter)]
+    pub voter_token_account: Account<'info, TokenAccount>,
 
     #[account(mut)]
-    pub listing_mint: Box<Account<'info, Mint>>,
+    pub voter: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteProposal<'info> {
+    #[account(mut, has_one = governance)]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(has_one = charter)]
+    pub governance: Account<'info, Governance>,
+
+    #[account(mut)]
+    pub charter: Account<'info, Charter>,
 
-    /// CHECK: PDA seed validation via seeds constraint
     #[account(
-        seeds = [b"mint", listing_mint.key().as_ref()],
-        bump = listing_mint_bump,
+        init,
+        seeds = [b"pending", charter.key().as_ref(), &charter.change_count.to_le_bytes()],
+        bump,
+        payer = executor,
+        space = 8 + 1 + 32 + 8 + CharterChange::SPACE + 32 + 8 + 8
     )]
-    pub listing_mint_authority: AccountInfo<'info>,
+    pub pending: Account<'info, PendingCharterChange>,
+
+    #[account(mut)]
+    pub executor: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
 
+#[derive(Accounts)]
+#[instruction(multisig_bump: u8, members: Vec<Pubkey>)]
+pub struct CreateMultisig<'info> {
     #[account(
-        has_one = charter,
-        constraint = charter_treasury_deposit.key() == charter_treasury.clone().into_inner().deposit.key(),
-        constraint = charter_treasury.mint == listings_payment_deposit.mint,
+        init,
+        seeds = [b"multisig", create_key.key().as_ref()],
+        bump,
+        payer = payer,
+        space = 8 + 1 + 32 + 4 + 32 * MAX_MULTISIG_MEMBERS + 1 + 4 + 8
     )]
-    pub charter_treasury: Box<Account<'info, CharterTreasury>>,
+    pub multisig: Account<'info, Multisig>,
 
-    #[account(mut)]
-    pub charter_treasury_deposit: Box<Account<'info, TokenAccount>>,
+    /// A one-off key that makes the multisig's address unique.
+    pub create_key: Signer<'info>,
 
     #[account(mut)]
-    pub charter_vote_deposit: Box<Account<'info, TokenAccount>>,
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
 
+#[derive(Accounts)]
+#[instruction(signer_bump: u8)]
+pub struct SetMultisigMembers<'info> {
     #[account(mut)]
-    pub charter_mint: Box<Account<'info, Mint>>,
+    pub multisig: Account<'info, Multisig>,
 
-    /// CHECK: PDA seed validation via seeds constraint
-    #[account(
-        seeds = [b"mint", charter_mint.key().as_ref()],
-        bump = charter_mint_bump,
-    )]
-    pub charter_mint_authority: AccountInfo<'info>,
+    #[account(seeds = [b"signer", multisig.key().as_ref()], bump = signer_bump)]
+    pub signer: Signer<'info>,
+}
 
+#[derive(Accounts)]
+#[instruction(transaction_bump: u8, accounts: Vec<TransactionAccount>, data: Vec<u8>)]
+pub struct ProposeTransaction<'info> {
     #[account(
-        constraint = charter.clone().into_inner().mint == charter_mint.key()
+        init,
+        seeds = [b"transaction", multisig.key().as_ref(), &multisig.transaction_count.to_le_bytes()],
+        bump,
+        payer = proposer,
+        space = 8 + 1 + 32 + 32 + 8 + 4 + 4 + accounts.len() * (32 + 1 + 1) + 4 + data.len() + 2 + 1
     )]
-    pub charter: Box<Account<'info, Charter>>,
+    pub transaction: Account<'info, MultisigTransaction>,
 
-    pub token_program: Program<'info, Token>,
+    #[account(mut)]
+    pub multisig: Account<'info, Multisig>,
+
+    #[account(mut)]
+    pub proposer: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-#[instruction(listing_bump: u8, listing_mint_authority_bump: u8)]
-pub struct Cancel<'info> {
-    pub purchaser: Signer<'info>,
+pub struct ApproveTransaction<'info> {
+    #[account(mut, has_one = multisig)]
+    pub transaction: Account<'info, MultisigTransaction>,
 
-    #[account(mut)]
-    pub return_deposit: Account<'info, TokenAccount>,
+    pub multisig: Account<'info, Multisig>,
+
+    pub member: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(signer_bump: u8)]
+pub struct ExecuteTransaction<'info> {
+    #[account(mut, has_one = multisig)]
+    pub transaction: Account<'info, MultisigTransaction>,
+
+    // Not `mut`: the wrapped instruction may update the multisig itself.
+    pub multisig: Account<'info, Multisig>,
+
+    /// CHECK: PDA seed validation via seeds constraint
+    #[account(seeds = [b"signer", multisig.key().as_ref()], bump = signer_bump)]
+    pub signer: AccountInfo<'info>,
+
+    pub member: Signer<'info>,
+}
+
+// ─── State Accounts ─────────────────────────────────────────────────────────
+
+#[account]
+pub struct Receipt {
+    pub is_initialized: bool,
+    pub is_refundable: bool,
+    pub is_cashable: bool,
+    pub listing: Pubkey,
+    pub listing_token_account: Pubkey,
+    pub purchaser: Pubkey,
+    pub cashier: Pubkey,
+    pub escrow: Pubkey,
+    pub quantity: u64,
+    pub price: u64,
+    pub nonce: u128,
+    pub purchased_at: i64,
+    pub is_disputed: bool,
+    pub was_disputed: bool,
+}
+
+/// A purchaser's claim against an uncashed receipt, ruled on by the charter's
+/// arbiter. Past `deadline`, anyone can settle it with the charter's default.
+#[account]
+pub struct Dispute {
+    pub is_initialized: bool,
+    pub receipt: Pubkey,
+    pub charter: Pubkey,
+    pub purchaser: Pubkey,
+    pub opened_at: i64,
+    pub deadline: i64,
+    pub uri: String,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum DisputeRuling {
+    Refund,
+    Release,
+}
+
+/// Marks a single license token account as revoked, e.g. after fraud or a
+/// chargeback. Reinstating keeps the record with `is_revoked` cleared. A
+/// non-zero `expires_at` makes the license lapse at that time.
+#[account]
+pub struct LicenseRevocation {
+    pub is_initialized: bool,
+    pub listing: Pubkey,
+    pub listing_token_account: Pubkey,
+    pub is_revoked: bool,
+    pub revoked_at: i64,
+    pub authority: Pubkey,
+    pub uri: String,
+    pub expires_at: i64,
+}
+
+/// Marks a signed nonce as spent, at `[b"nonce", listing, holder, nonce]` for consume
+/// attestations and `[b"voucher", listing, nonce]` for purchase vouchers. Its
+/// address is all that matters.
+#[account]
+pub struct ConsumedNonce {}
+
+/// A price quoted off-chain by the listing's pricing key for one buyer.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PurchaseVoucher {
+    pub price: u64,
+    pub expires_at: i64,
+    pub nonce: u64,
+}
+
+impl PurchaseVoucher {
+    /// The message the pricing key signs. The payment mint is bound too, so a
+    /// price quoted in one token can't be spent in another.
+    pub fn message(&self, listing: &Pubkey, buyer: &Pubkey, mint: &Pubkey) -> Vec<u8> {
+        [
+            b"strangemood:voucher".as_ref(),
+            listing.as_ref(),
+            buyer.as_ref(),
+            mint.as_ref(),
+            &self.price.to_le_bytes(),
+            &self.expires_at.to_le_bytes(),
+            &self.nonce.to_le_bytes(),
+        ]
+        .concat()
+    }
+}
+
+pub const SESSION_SCOPE_CONSUME: u8 = 1 << 0;
+
+/// Lets an ephemeral `session_key` act for the holder of a license token account,
+/// within `scope` and up to `max_units`, until `expires_at`.
+#[account]
+pub struct Session {
+    pub is_initialized: bool,
+    pub holder: Pubkey,
+    pub listing_token_account: Pubkey,
+    pub session_key: Pubkey,
+    pub scope: u8,
+    pub max_units: u64,
+    pub used_units: u64,
+    pub expires_at: i64,
+}
+
+impl Session {
+    pub fn authorize(&mut self, scope: u8, units: u64, now: i64) -> Result<()> {
+        if self.scope & scope != scope {
+            return Err(StrangemoodError::SessionScopeExceeded.into());
+        }
+        if now >= self.expires_at {
+            return Err(StrangemoodError::SessionExpired.into());
+        }
+        let used_units = self.used_units.saturating_add(units);
+        if used_units > self.max_units {
+            return Err(StrangemoodError::SessionScopeExceeded.into());
+        }
+        self.used_units = used_units;
+        Ok(())
+    }
+}
+
+pub const ROLE_PRICE_MANAGER: u8 = 1 << 0;
+pub const ROLE_METADATA_EDITOR: u8 = 1 << 1;
+pub const ROLE_SUPPORT: u8 = 1 << 2;
+pub const ROLE_CASHIER: u8 = 1 << 3;
+pub const ROLE_CONSUMER: u8 = 1 << 4;
+pub const ROLE_ALL: u8 =
+    ROLE_PRICE_MANAGER | ROLE_METADATA_EDITOR | ROLE_SUPPORT | ROLE_CASHIER | ROLE_CONSUMER;
+
+/// Capabilities the listing authority has delegated to `grantee`, at
+/// `[b"role", listing, grantee]`.
+#[account]
+pub struct ListingRole {
+    pub is_initialized: bool,
+    pub listing: Pubkey,
+    pub grantee: Pubkey,
+    pub roles: u8,
+    /// The listing's `role_version` when these roles were granted.
+    pub version: u64,
+}
+
+impl ListingRole {
+    /// Whether `role` lets `key` act on `listing` with every one of `roles`.
+    pub fn grants(
+        role: Option<&ListingRole>,
+        listing: &Pubkey,
+        role_version: u64,
+        key: &Pubkey,
+        roles: u8,
+    ) -> bool {
+        role.is_some_and(|r| {
+            r.listing == *listing
+                && r.version == role_version
+                && r.grantee == *key
+                && r.roles & roles == roles
+        })
+    }
+
+    /// Whether `account`, a `[b"role", listing, key]` address that may be
+    /// empty, holds any current role on `listing`.
+    pub fn is_held(account: &AccountInfo, listing: &Pubkey, role_version: u64) -> Result<bool> {
+        if account.owner != &crate::ID || account.data_is_empty() {
+            return Ok(false);
+        }
+        let role = ListingRole::try_deserialize(&mut &account.data.borrow()[..])?;
+        Ok(role.listing == *listing && role.version == role_version && role.roles != 0)
+    }
+}
+
+pub const MAX_CODE_BATCH_SIZE: u32 = 8192;
+
+/// A batch of secret key codes, committed as a Merkle root over
+/// `code_leaf(index, code_key(code))`. Redeemed codes are tracked in `bitmap`.
+#[account]
+pub struct CodeBatch {
+    pub is_initialized: bool,
+    pub listing: Pubkey,
+    pub batch_id: u64,
+    pub root: [u8; 32],
+    pub size: u32,
+    pub redeemed: u32,
+    pub bitmap: Vec<u8>,
+}
 
-    #[account(mut, has_one = listing, has_one = listing_token_account, has_one = purchaser, has_one = escrow)]
-    pub receipt: Account<'info, Receipt>,
+impl CodeBatch {
+    pub fn is_redeemed(&self, index: u32) -> bool {
+        self.bitmap[index as usize / 8] & (1 << (index % 8)) != 0
+    }
 
-    #[account(mut)]
-    pub escrow: Account<'info, TokenAccount>,
+    pub fn set_redeemed(&mut self, index: u32) {
+        self.bitmap[index as usize / 8] |= 1 << (index % 8);
+    }
+}
 
-    /// CHECK: PDA used as escrow authority
-    pub escrow_authority: AccountInfo<'info>,
+/// The leaf committed for code number `index`: `sha256(index || code_key)`,
+/// where `code_key` is the ed25519 public key whose seed is `sha256(code)`.
+pub fn code_leaf(index: u32, code_key: &Pubkey) -> [u8; 32] {
+    hashv(&[&index.to_le_bytes(), code_key.as_ref()]).to_bytes()
+}
 
-    #[account(mut)]
-    pub listing_token_account: Box<Account<'info, TokenAccount>>,
+/// The message a code's key signs to hand the code to `redeemer`.
+pub fn redeem_code_message(code_batch: &Pubkey, index: u32, redeemer: &Pubkey) -> Vec<u8> {
+    [
+        b"strangemood:redeem".as_ref(),
+        code_batch.as_ref(),
+        &index.to_le_bytes(),
+        redeemer.as_ref(),
+    ]
+    .concat()
+}
 
-    #[account(seeds = [b"listing", listing_mint.key().as_ref()], bump = listing_bump)]
-    pub listing: Box<Account<'info, Listing>>,
+pub const MAX_DISTRIBUTOR_CLAIMS: u32 = 65536;
 
-    pub listing_mint: Box<Account<'info, Mint>>,
+/// Airdrops licenses to wallets committed as a Merkle root over
+/// `airdrop_leaf(index, wallet, listing, quantity)`. Claims are tracked in `bitmap`.
+#[account]
+pub struct Distributor {
+    pub is_initialized: bool,
+    pub listing: Pubkey,
+    pub distributor_id: u64,
+    pub root: [u8; 32],
+    pub max_claims: u32,
+    pub claimed: u32,
+    pub claimed_quantity: u64,
+    pub bitmap: Vec<u8>,
+}
 
-    /// CHECK: PDA seed validation via seeds constraint
-    #[account(
-        seeds = [b"mint", listing_mint.key().as_ref()],
-        bump = listing_mint_authority_bump,
-    )]
-    pub listing_mint_authority: AccountInfo<'info>,
+impl Distributor {
+    pub fn is_claimed(&self, index: u32) -> bool {
+        self.bitmap[index as usize / 8] & (1 << (index % 8)) != 0
+    }
 
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
+    pub fn set_claimed(&mut self, index: u32) {
+        self.bitmap[index as usize / 8] |= 1 << (index % 8);
+    }
 }
 
-#[derive(Accounts)]
-#[instruction(listing_bump: u8, listing_mint_authority_bump: u8)]
-pub struct Consume<'info> {
-    #[account(seeds = [b"listing", mint.key().as_ref()], bump = listing_bump, has_one = authority, has_one = mint)]
-    pub listing: Box<Account<'info, Listing>>,
+/// The leaf committed for claim number `index`:
+/// `sha256(index || wallet || listing || quantity)`.
+pub fn airdrop_leaf(index: u32, wallet: &Pubkey, listing: &Pubkey, quantity: u64) -> [u8; 32] {
+    hashv(&[
+        &index.to_le_bytes(),
+        wallet.as_ref(),
+        listing.as_ref(),
+        &quantity.to_le_bytes(),
+    ])
+    .to_bytes()
+}
 
-    pub mint: Box<Account<'info, Mint>>,
+/// Returned by `verify_license`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct LicenseVerification {
+    pub is_valid: bool,
+    pub listing: Pubkey,
+    pub holder: Pubkey,
+    pub quantity: u64,
+    pub is_frozen: bool,
+    pub is_revoked: bool,
+    pub expires_at: i64,
+}
 
-    /// CHECK: PDA seed validation via seeds constraint
-    #[account(
-        seeds = [b"mint", mint.key().as_ref()],
-        bump = listing_mint_authority_bump,
-    )]
-    pub mint_authority: AccountInfo<'info>,
+#[account]
+pub struct Listing {
+    pub is_initialized: bool,
+    pub is_available: bool,
+    pub charter: Pubkey,
+    pub authority: Pubkey,
+    pub payment_deposit: Pubkey,
+    pub vote_deposit: Pubkey,
+    pub price: u64,
+    pub mint: Pubkey,
+    pub uri: String,
+    pub is_refundable: bool,
+    pub is_consumable: bool,
+    pub is_campaign: bool,
+    pub is_milestone_funded: bool,
+    pub refund_fee_bps: u16,
+    pub refund_window: i64,
+    pub is_refund_fee_decaying: bool,
+    pub attestation_key: Pubkey,
+    pub pricing_key: Pubkey,
+    pub pending_authority: Pubkey,
+    /// Once set, purchases and settlements must pass the `[b"prices", listing]` table.
+    pub has_price_table: bool,
+    /// Receipts not yet cashed, cancelled or refunded.
+    pub open_receipts: u64,
+    /// Bumped on every authority handover; roles granted under an older
+    /// version no longer apply.
+    pub role_version: u64,
+    /// Disputes opened and not yet ruled on.
+    pub open_disputes: u64,
+}
 
-    #[account(mut)]
-    pub listing_token_account: Box<Account<'info, TokenAccount>>,
-    pub token_program: Program<'info, Token>,
+/// Funding progress of a listing in campaign mode. Receipts stay in escrow
+/// until `goal` is raised; if `deadline` passes first, every backer may cancel.
+#[account]
+pub struct Campaign {
+    pub is_initialized: bool,
+    pub listing: Pubkey,
+    pub mint: Pubkey,
+    pub goal: u64,
+    pub deadline: i64,
+    pub raised: u64,
+    pub backers: u64,
+    pub is_funded: bool,
+}
 
-    pub authority: Signer<'info>,
+impl Campaign {
+    /// Takes `amount` of a cancelled or refunded backing out of the totals,
+    /// along with its backer when the receipt closes.
+    pub fn withdraw(&mut self, amount: u64, is_closing: bool) -> Result<()> {
+        self.raised = self
+            .raised
+            .checked_sub(amount)
+            .ok_or(StrangemoodError::UnbackedReceipt)?;
+        if is_closing {
+            self.backers = self
+                .backers
+                .checked_sub(1)
+                .ok_or(StrangemoodError::UnbackedReceipt)?;
+        }
+        Ok(())
+    }
 }
 
-#[derive(Accounts)]
-pub struct SetReceiptCashable<'info> {
-    #[account(has_one = authority)]
-    pub listing: Box<Account<'info, Listing>>,
+pub const MAX_MILESTONES: usize = 8;
 
-    #[account(mut, has_one = listing)]
-    pub receipt: Account<'info, Receipt>,
+/// Holds a listing's cashed revenue until the charter approves its milestones.
+/// Once abandoned, the remaining funds are refunded pro rata per cashed license.
+#[account]
+pub struct MilestoneVault {
+    pub is_initialized: bool,
+    pub listing: Pubkey,
+    pub vault: Pubkey,
+    pub beneficiary: Pubkey,
+    pub milestones: Vec<Milestone>,
+    pub deposited: u64,
+    pub released: u64,
+    pub licenses: u64,
+    pub is_abandoned: bool,
+    pub refund_pool: u64,
+    pub refunded_licenses: u64,
+}
 
-    pub authority: Signer<'info>,
+/// The licenses one purchaser cashed into a milestone vault, which caps what
+/// they can claim back if the milestones are abandoned.
+#[account]
+pub struct MilestoneBacking {
+    pub is_initialized: bool,
+    pub milestones: Pubkey,
+    pub purchaser: Pubkey,
+    pub licenses: u64,
+    pub refunded_licenses: u64,
 }
 
-#[derive(Accounts)]
-#[instruction(mint_bump: u8, listing_bump: u8, listing_mint_decimals: u8)]
-pub struct InitListing<'info> {
-    #[account(
-        init,
-        seeds = [b"listing", mint.key().as_ref()],
-        bump,
-        payer = user,
-        space = 8 + 235 + 128
-    )]
-    pub listing: Box<Account<'info, Listing>>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct Milestone {
+    pub release_bps: u16,
+    pub is_approved: bool,
+}
 
-    /// CHECK: PDA seed validation via seeds constraint
-    #[account(
-        seeds = [b"mint", mint.key().as_ref()],
-        bump = mint_bump,
-    )]
-    pub mint_authority_pda: AccountInfo<'info>,
+impl MilestoneVault {
+    /// Revenue unlocked by approved milestones that hasn't been paid out yet.
+    pub fn releasable(&self) -> u64 {
+        let approved_bps: u64 = self
+            .milestones
+            .iter()
+            .filter(|m| m.is_approved)
+            .map(|m| m.release_bps as u64)
+            .sum();
+        let unlocked = (self.deposited as u128 * approved_bps as u128 / 10_000) as u64;
+        unlocked.saturating_sub(self.released)
+    }
+}
 
-    #[account(
-        init,
-        mint::decimals = listing_mint_decimals,
-        mint::authority = mint_authority_pda,
-        mint::freeze_authority = mint_authority_pda,
-        payer = user
-    )]
-    pub mint: Box<Account<'info, Mint>>,
+pub const MAX_PRICE_TABLE_ENTRIES: usize = 8;
 
-    pub payment_deposit: Box<Account<'info, TokenAccount>>,
-    pub vote_deposit: Box<Account<'info, TokenAccount>>,
+#[account]
+pub struct ListingPriceTable {
+    pub is_initialized: bool,
+    pub listing: Pubkey,
+    pub entries: Vec<PriceTableEntry>,
+}
 
-    pub charter: Box<Account<'info, Charter>>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct PriceTableEntry {
+    pub mint: Pubkey,
+    /// Unit price in `mint`, or in USD with `USD_DECIMALS` when `oracle` is set.
+    pub price: u64,
+    pub payment_deposit: Pubkey,
+    pub oracle: Option<OracleConfig>,
+}
 
-    #[account(has_one = charter)]
-    pub charter_treasury: Box<Account<'info, CharterTreasury>>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct OracleConfig {
+    pub kind: PriceFeedKind,
+    pub price_feed: Pubkey,
+    pub max_staleness: u32,
+    pub max_confidence_bps: u16,
+}
 
-    pub rent: Sysvar<'info, Rent>,
-    pub token_program: Program<'info, Token>,
+/// The resolved price of a listing for one payment mint.
+pub struct PaymentTerms {
+    pub price: u64,
+    pub oracle: Option<OracleConfig>,
+}
 
-    #[account(mut)]
-    pub user: Signer<'info>,
-    pub system_program: Program<'info, System>,
+/// Votes minted under a charter, for its emission caps.
+#[account]
+pub struct CharterEmissions {
+    pub is_initialized: bool,
+    pub charter: Pubkey,
+    pub epoch: u64,
+    pub epoch_minted: u64,
+    pub total_minted: u64,
 }
 
-#[derive(Accounts)]
-pub struct SetListing<'info> {
-    #[account(mut)]
-    pub listing: Account<'info, Listing>,
+impl CharterEmissions {
+    /// Clamps `votes` to what the charter's caps leave in `epoch` and records
+    /// the result as minted.
+    pub fn record(&mut self, charter: &Charter, epoch: u64, votes: u64) -> u64 {
+        if epoch != self.epoch {
+            self.epoch = epoch;
+            self.epoch_minted = 0;
+        }
+        let cap = |cap: u64| if cap == 0 { u64::MAX } else { cap };
+        let votes = votes
+            .min(cap(charter.emission_epoch_cap).saturating_sub(self.epoch_minted))
+            .min(cap(charter.emission_lifetime_cap).saturating_sub(self.total_minted));
+        self.epoch_minted += votes;
+        self.total_minted += votes;
+        votes
+    }
+}
 
-    #[account(mut)]
-    pub user: Signer<'info>,
-    pub system_program: Program<'info, System>,
+/// Votes `cash` minted to a listing, released to its `vote_deposit` after the
+/// charter's cliff and then linearly. Deposits merge into a single schedule
+/// whose dates are the amount-weighted average of theirs.
+#[account]
+pub struct VoteVesting {
+    pub is_initialized: bool,
+    pub listing: Pubkey,
+    pub vault: Pubkey,
+    /// Still vesting.
+    pub locked: u64,
+    /// Vested but not yet claimed.
+    pub released: u64,
+    pub cliff_at: i64,
+    pub end_at: i64,
+    pub claimed: u64,
+    /// The charter mint being vested; a listing has one escrow per charter mint.
+    pub mint: Pubkey,
 }
 
-#[derive(Accounts)]
-pub struct SetListingDeposit<'info> {
-    #[account(mut)]
-    pub listing: Account<'info, Listing>,
+impl VoteVesting {
+    /// How much of `locked` has vested by `now`.
+    pub fn vested(&self, now: i64) -> u64 {
+        if self.locked == 0 || now < self.cliff_at {
+            0
+        } else if now >= self.end_at {
+            self.locked
+        } else {
+            (self.locked as u128 * (now - self.cliff_at) as u128
+                / (self.end_at - self.cliff_at) as u128) as u64
+        }
+    }
 
-    pub payment_deposit: Account<'info, TokenAccount>,
-    pub vote_deposit: Account<'info, TokenAccount>,
+    /// Moves what has vested into `released`; the rest keeps vesting until
+    /// `end_at`.
+    fn settle(&mut self, now: i64) {
+        let vested = self.vested(now);
+        self.locked -= vested;
+        self.released += vested;
+        if now > self.cliff_at {
+            self.cliff_at = now;
+        }
+    }
 
-    #[account(mut)]
-    pub user: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+    pub fn deposit(&mut self, amount: u64, now: i64, cliff: i64, duration: i64) {
+        self.settle(now);
+        let cliff_at = now + cliff;
+        let end_at = cliff_at + duration;
+        if self.locked == 0 {
+            self.cliff_at = cliff_at;
+            self.end_at = end_at;
+        } else {
+            let weighted = |old: i64, new: i64| {
+                ((old as i128 * self.locked as i128 + new as i128 * amount as i128)
+                    / (self.locked as i128 + amount as i128)) as i64
+            };
+            self.cliff_at = weighted(self.cliff_at, cliff_at);
+            self.end_at = weighted(self.end_at, end_at);
+        }
+        self.locked += amount;
+    }
 
-#[derive(Accounts)]
-pub struct SetListingAuthority<'info> {
-    #[account(mut)]
-    pub listing: Account<'info, Listing>,
+    /// Settles and returns everything claimable at `now`.
+    pub fn claim(&mut self, now: i64) -> u64 {
+        self.settle(now);
+        let amount = self.released;
+        self.released = 0;
+        self.claimed += amount;
+        amount
+    }
+}
 
-    /// CHECK: new authority, no validation needed
-    pub authority: AccountInfo<'info>,
+// ─── Governance ─────────────────────────────────────────────────────────────
 
-    #[account(mut)]
-    pub user: Signer<'info>,
-    pub system_program: Program<'info, System>,
+/// Governs a charter once the charter's authority is handed to this account.
+/// A proposal passes when at least `quorum` charter tokens vote and at least
+/// `threshold_bps` of them approve.
+#[account]
+pub struct Governance {
+    pub is_initialized: bool,
+    pub charter: Pubkey,
+    pub vault: Pubkey,
+    pub quorum: u64,
+    pub threshold_bps: u16,
+    pub voting_period: i64,
+    pub proposal_count: u64,
 }
 
-#[derive(Accounts)]
-pub struct SetListingCharter<'info> {
-    #[account(mut)]
-    pub listing: Account<'info, Listing>,
+impl Governance {
+    pub fn set_voting_rules(
+        &mut self,
+        quorum: u64,
+        threshold_bps: u16,
+        voting_period: i64,
+    ) -> Result<()> {
+        if threshold_bps == 0 || threshold_bps > 10_000 || voting_period <= 0 {
+            return Err(StrangemoodError::InvalidVotingRules.into());
+        }
+        self.quorum = quorum;
+        self.threshold_bps = threshold_bps;
+        self.voting_period = voting_period;
+        Ok(())
+    }
 
-    pub charter: Account<'info, Charter>,
+    pub fn check_passed(&self, proposal: &Proposal) -> Result<()> {
+        let total = proposal.yes_votes as u128 + proposal.no_votes as u128;
+        if total < self.quorum as u128 {
+            return Err(StrangemoodError::QuorumNotReached.into());
+        }
+        if total == 0 || proposal.yes_votes as u128 * 10_000 < total * self.threshold_bps as u128 {
+            return Err(StrangemoodError::ThresholdNotReached.into());
+        }
+        Ok(())
+    }
+}
 
-    #[account(mut)]
-    pub user: Signer<'info>,
-    pub system_program: Program<'info, System>,
+#[account]
+pub struct Proposal {
+    pub is_initialized: bool,
+    pub governance: Pubkey,
+    pub proposer: Pubkey,
+    pub id: u64,
+    pub change: CharterChange,
+    pub uri: String,
+    pub created_at: i64,
+    pub voting_ends_at: i64,
+    pub yes_votes: u64,
+    pub no_votes: u64,
+    pub is_executed: bool,
 }
 
-#[derive(Accounts)]
-#[instruction(charter_bump: u8)]
-pub struct InitCharter<'info> {
-    #[account(
-        init,
-        seeds = [b"charter", mint.key().as_ref()],
-        bump,
-        payer = user,
-        space = 8 + 8 + 1 + 8 + 1 + 8 + 1 + 32 + 32 + 32 + 128 + 256
-    )]
-    pub charter: Account<'info, Charter>,
+/// One voter's locked charter tokens on a proposal.
+#[account]
+pub struct VoteRecord {
+    pub is_initialized: bool,
+    pub proposal: Pubkey,
+    pub voter: Pubkey,
+    pub amount: u64,
+    pub is_approve: bool,
+}
 
-    pub mint: Account<'info, Mint>,
+/// A parameter change carried by a proposal.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CharterChange {
+    ExpansionRate {
+        amount: u64,
+        decimals: u8,
+    },
+    ContributionRate {
+        payment_amount: u64,
+        payment_decimals: u8,
+        vote_amount: u64,
+        vote_decimals: u8,
+    },
+    TreasuryExpansionScalar {
+        treasury: Pubkey,
+        amount: u64,
+        decimals: u8,
+    },
+    Authority {
+        authority: Pubkey,
+    },
+    Arbiter {
+        arbiter: Pubkey,
+    },
+    DisputePolicy {
+        dispute_period: i64,
+        is_dispute_default_refund: bool,
+    },
+    CodeFee {
+        is_code_fee_charged: bool,
+    },
+    ChangeDelay {
+        change_delay: i64,
+    },
+    VoteDeposit {
+        vote_deposit: Pubkey,
+    },
+    TreasuryDeposit {
+        treasury: Pubkey,
+        deposit: Pubkey,
+    },
+    VotingRules {
+        quorum: u64,
+        threshold_bps: u16,
+        voting_period: i64,
+    },
+    TreasuryLimit {
+        treasury: Pubkey,
+        epoch_duration: i64,
+        epoch_limit: u64,
+    },
+    VoteVesting {
+        cliff: i64,
+        duration: i64,
+    },
+    EmissionPolicy {
+        decay_bps: u16,
+        epoch_duration: i64,
+        epoch_cap: u64,
+        lifetime_cap: u64,
+    },
+    BuyerVoteRate {
+        amount: u64,
+        decimals: u8,
+    },
+    /// Carried out by `withdraw_from_treasury` rather than `apply_charter_change`.
+    TreasuryWithdrawal {
+        treasury: Pubkey,
+        destination: Pubkey,
+        amount: u64,
+    },
+    /// Carried out by `approve_milestone`.
+    ApproveMilestone {
+        milestones: Pubkey,
+        index: u8,
+    },
+    /// Carried out by `abandon_milestones`.
+    AbandonMilestones {
+        milestones: Pubkey,
+    },
+}
 
-    /// CHECK: authority stored in charter, validated in set instructions
-    pub authority: AccountInfo<'info>,
+impl CharterChange {
+    /// The largest variant, `TreasuryWithdrawal`, plus its tag.
+    pub const SPACE: usize = 1 + 32 + 32 + 8;
+
+    /// The treasury a change applies to, if any.
+    pub fn treasury(&self) -> Option<Pubkey> {
+        match *self {
+            CharterChange::TreasuryExpansionScalar { treasury, .. }
+            | CharterChange::TreasuryDeposit { treasury, .. }
+            | CharterChange::TreasuryLimit { treasury, .. }
+            | CharterChange::TreasuryWithdrawal { treasury, .. } => Some(treasury),
+            _ => None,
+        }
+    }
 
-    pub vote_deposit: Account<'info, TokenAccount>,
+    /// Checks the change against the bounds every charter obeys and the
+    /// charter's own caps.
+    pub fn validate(&self, charter: &Charter) -> Result<()> {
+        match *self {
+            CharterChange::ExpansionRate { decimals, .. } => check_decimals(decimals),
+            CharterChange::ContributionRate {
+                payment_amount,
+                payment_decimals,
+                vote_amount,
+                vote_decimals,
+            } => {
+                check_rate(
+                    payment_amount,
+                    payment_decimals,
+                    cap_or_uncapped(charter.payment_contribution_cap_bps),
+                )?;
+                check_rate(
+                    vote_amount,
+                    vote_decimals,
+                    cap_or_uncapped(charter.vote_contribution_cap_bps),
+                )
+            }
+            CharterChange::TreasuryExpansionScalar {
+                amount, decimals, ..
+            } => check_expansion_scalar(amount, decimals),
+            CharterChange::DisputePolicy { dispute_period, .. } if dispute_period <= 0 => {
+                Err(StrangemoodError::InvalidDisputePeriod.into())
+            }
+            CharterChange::ChangeDelay { change_delay }
+                if change_delay < 0 || change_delay < charter.min_change_delay =>
+            {
+                Err(StrangemoodError::InvalidChangeDelay.into())
+            }
+            CharterChange::TreasuryLimit { epoch_duration, .. } => {
+                check_treasury_limit(epoch_duration)
+            }
+            CharterChange::VoteVesting { cliff, duration } if cliff < 0 || duration < 0 => {
+                Err(StrangemoodError::InvalidVestingSchedule.into())
+            }
+            CharterChange::EmissionPolicy {
+                decay_bps,
+                epoch_duration,
+                ..
+            } if decay_bps > 10_000 || epoch_duration < 0 => {
+                Err(StrangemoodError::InvalidEmissionPolicy.into())
+            }
+            CharterChange::BuyerVoteRate { amount, decimals } => check_rate(
+                amount,
+                decimals,
+                cap_or_uncapped(charter.buyer_vote_cap_bps),
+            ),
+            CharterChange::TreasuryWithdrawal { amount: 0, .. } => {
+                Err(StrangemoodError::InvalidQuantity.into())
+            }
+            _ => Ok(()),
+        }
+    }
 
-    #[account(mut)]
-    pub user: Signer<'info>,
-    pub system_program: Program<'info, System>,
+    /// Applies the change. `treasury` must already be checked against
+    /// `self.treasury()`.
+    pub fn apply(
+        &self,
+        charter: &mut Charter,
+        governance: Option<&mut Governance>,
+        treasury: Option<&mut CharterTreasury>,
+    ) -> Result<()> {
+        match *self {
+            CharterChange::ExpansionRate { amount, decimals } => {
+                charter.expansion_rate_amount = amount;
+                charter.expansion_rate_decimals = decimals;
+            }
+            CharterChange::ContributionRate {
+                payment_amount,
+                payment_decimals,
+                vote_amount,
+                vote_decimals,
+            } => {
+                charter.payment_contribution_rate_amount = payment_amount;
+                charter.payment_contribution_rate_decimals = payment_decimals;
+                charter.vote_contribution_rate_amount = vote_amount;
+                charter.vote_contribution_rate_decimals = vote_decimals;
+            }
+            CharterChange::TreasuryExpansionScalar {
+                amount, decimals, ..
+            } => {
+                let treasury = treasury.ok_or(StrangemoodError::TreasuryRequired)?;
+                treasury.expansion_scalar_amount = amount;
+                treasury.expansion_scalar_decimals = decimals;
+            }
+            CharterChange::Authority { authority } => charter.pending_authority = authority,
+            CharterChange::Arbiter { arbiter } => charter.arbiter = arbiter,
+            CharterChange::DisputePolicy {
+                dispute_period,
+                is_dispute_default_refund,
+            } => {
+                charter.dispute_period = dispute_period;
+                charter.is_dispute_default_refund = is_dispute_default_refund;
+            }
+            CharterChange::CodeFee {
+                is_code_fee_charged,
+            } => charter.is_code_fee_charged = is_code_fee_charged,
+            CharterChange::ChangeDelay { change_delay } => charter.change_delay = change_delay,
+            CharterChange::VoteDeposit { vote_deposit } => charter.vote_deposit = vote_deposit,
+            CharterChange::TreasuryDeposit { deposit, .. } => {
+                let treasury = treasury.ok_or(StrangemoodError::TreasuryRequired)?;
+                treasury.deposit = deposit;
+            }
+            CharterChange::VotingRules {
+                quorum,
+                threshold_bps,
+                voting_period,
+            } => {
+                let governance = governance.ok_or(StrangemoodError::GovernanceRequired)?;
+                governance.set_voting_rules(quorum, threshold_bps, voting_period)?;
+            }
+            CharterChange::TreasuryLimit {
+                epoch_duration,
+                epoch_limit,
+                ..
+            } => {
+                let treasury = treasury.ok_or(StrangemoodError::TreasuryRequired)?;
+                treasury.epoch_duration = epoch_duration;
+                treasury.epoch_limit = epoch_limit;
+            }
+            CharterChange::VoteVesting { cliff, duration } => {
+                charter.vote_vesting_cliff = cliff;
+                charter.vote_vesting_duration = duration;
+            }
+            CharterChange::EmissionPolicy {
+                decay_bps,
+                epoch_duration,
+                epoch_cap,
+                lifetime_cap,
+            } => {
+                charter.set_emission_schedule(epoch_duration, Clock::get()?.unix_timestamp)?;
+                charter.emission_decay_bps = decay_bps;
+                charter.emission_epoch_cap = epoch_cap;
+                charter.emission_lifetime_cap = lifetime_cap;
+            }
+            CharterChange::BuyerVoteRate { amount, decimals } => {
+                charter.buyer_vote_rate_amount = amount;
+                charter.buyer_vote_rate_decimals = decimals;
+            }
+            CharterChange::TreasuryWithdrawal { .. }
+            | CharterChange::ApproveMilestone { .. }
+            | CharterChange::AbandonMilestones { .. } => {
+                return Err(StrangemoodError::UnexpectedCharterChange.into())
+            }
+        }
+        Ok(())
+    }
 }
 
-#[derive(Accounts)]
-pub struct SetCharter<'info> {
-    #[account(mut)]
-    pub charter: Account<'info, Charter>,
+/// A charter change waiting out the charter's `change_delay`. Anyone can apply
+/// it from `effective_at`; the charter authority can cancel it before then.
+#[account]
+pub struct PendingCharterChange {
+    pub is_initialized: bool,
+    pub charter: Pubkey,
+    pub id: u64,
+    pub change: CharterChange,
+    pub payer: Pubkey,
+    pub queued_at: i64,
+    pub effective_at: i64,
+}
 
-    #[account(mut)]
-    pub user: Signer<'info>,
-    pub system_program: Program<'info, System>,
+/// Queues `change` on `charter` at the request of its authority, who pays for
+/// `pending`.
+pub fn queue_charter_change(
+    charter: &mut Account<Charter>,
+    pending: &mut PendingCharterChange,
+    authority: &Signer,
+    change: CharterChange,
+) -> Result<()> {
+    if authority.key() != charter.authority {
+        return Err(StrangemoodError::UnauthorizedAuthority.into());
+    }
+    let key = charter.key();
+    charter.queue_change(key, pending, change, authority.key())
+}
+
+/// Authorizes a decision the charter makes outside `apply_charter_change`:
+/// either `user` is the charter authority, or `pending` queued exactly `change`
+/// and has waited out its delay.
+pub fn check_charter_decision(
+    charter: &Charter,
+    pending: Option<&PendingCharterChange>,
+    user: &Pubkey,
+    change: CharterChange,
+) -> Result<()> {
+    match pending {
+        Some(pending) => {
+            if Clock::get()?.unix_timestamp < pending.effective_at {
+                return Err(StrangemoodError::ChangeNotYetEffective.into());
+            }
+            if pending.change != change {
+                return Err(StrangemoodError::UnexpectedCharterChange.into());
+            }
+        }
+        None => {
+            if *user != charter.authority {
+                return Err(StrangemoodError::UnauthorizedAuthority.into());
+            }
+        }
+    }
+    Ok(())
 }
 
-#[derive(Accounts)]
-pub struct SetCharterVoteDeposit<'info> {
-    #[account(mut)]
-    pub charter: Account<'info, Charter>,
+// ─── Multisig ───────────────────────────────────────────────────────────────
 
-    pub vote_deposit: Account<'info, TokenAccount>,
+pub const MAX_MULTISIG_MEMBERS: usize = 16;
 
-    pub user: Signer<'info>,
-    pub system_program: Program<'info, System>,
+/// An M-of-N multisig. Its `[b"signer", multisig]` PDA is what gets set as a
+/// listing or charter authority; fund it if wrapped instructions need a payer.
+#[account]
+pub struct Multisig {
+    pub is_initialized: bool,
+    pub create_key: Pubkey,
+    pub members: Vec<Pubkey>,
+    pub threshold: u8,
+    /// Bumped whenever the members change, which voids open transactions.
+    pub version: u32,
+    pub transaction_count: u64,
 }
 
-#[derive(Accounts)]
-pub struct SetCharterAuthority<'info> {
-    #[account(mut)]
-    pub charter: Account<'info, Charter>,
-
-    /// CHECK: new authority, no validation needed
-    pub authority: AccountInfo<'info>,
+impl Multisig {
+    pub fn set_members(&mut self, members: Vec<Pubkey>, threshold: u8) -> Result<()> {
+        let mut sorted = members.clone();
+        sorted.sort();
+        sorted.dedup();
+        if members.is_empty()
+            || members.len() > MAX_MULTISIG_MEMBERS
+            || sorted.len() != members.len()
+            || threshold == 0
+            || threshold as usize > members.len()
+        {
+            return Err(StrangemoodError::InvalidMultisig.into());
+        }
+        self.members = members;
+        self.threshold = threshold;
+        self.version += 1;
+        Ok(())
+    }
 
-    #[account(mut)]
-    pub user: Signer<'info>,
-    pub system_program: Program<'info, System>,
+    pub fn member_index(&self, key: &Pubkey) -> Result<usize> {
+        self.members
+            .iter()
+            .position(|m| m == key)
+            .ok_or_else(|| StrangemoodError::NotAMultisigMember.into())
+    }
 }
 
-#[derive(Accounts)]
-#[instruction(treasury_bump: u8)]
-pub struct InitCharterTreasury<'info> {
-    #[account(
-        init,
-        seeds = [b"treasury", charter.key().as_ref(), mint.key().as_ref()],
-        bump,
-        payer = authority,
-        space = 8 + 1 + 32 + 32 + 8 + 1 + 128
-    )]
-    pub treasury: Account<'info, CharterTreasury>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TransactionAccount {
+    pub pubkey: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
 
-    #[account(has_one = authority)]
-    pub charter: Account<'info, Charter>,
+/// A proposed instruction to this program, executed once enough members approve.
+/// `approvals` is a bitmask over member indices.
+#[account]
+pub struct MultisigTransaction {
+    pub is_initialized: bool,
+    pub multisig: Pubkey,
+    pub proposer: Pubkey,
+    pub index: u64,
+    pub version: u32,
+    pub accounts: Vec<TransactionAccount>,
+    pub data: Vec<u8>,
+    pub approvals: u16,
+    pub is_executed: bool,
+}
 
-    #[account(has_one = mint)]
-    pub deposit: Account<'info, TokenAccount>,
+// ─── Price Feeds ────────────────────────────────────────────────────────────
 
-    pub mint: Account<'info, Mint>,
+pub const USD_DECIMALS: u8 = 6;
 
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
+/// A price of one whole payment token in USD, scaled by `10^expo`.
+pub struct PriceQuote {
+    pub price: i64,
+    pub conf: u64,
+    pub expo: i32,
+    pub publish_time: i64,
 }
 
-#[derive(Accounts)]
-pub struct SetCharterTreasuryExpansionScalar<'info> {
-    #[account(mut, has_one = charter)]
-    pub treasury: Account<'info, CharterTreasury>,
-
-    #[account(has_one = authority)]
-    pub charter: Account<'info, Charter>,
-
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
+/// Reads a `PriceQuote` out of an oracle-owned account.
+pub trait PriceFeed {
+    fn load(&self, account: &AccountInfo) -> Result<PriceQuote>;
 }
 
-#[derive(Accounts)]
-pub struct SetCharterTreasuryDeposit<'info> {
-    #[account(mut, has_one = charter)]
-    pub treasury: Account<'info, CharterTreasury>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum PriceFeedKind {
+    Mock,
+    /// A fully verified Pyth `PriceUpdateV2`, e.g. a sponsored push feed account.
+    Pyth,
+}
 
-    #[account(has_one = authority)]
-    pub charter: Account<'info, Charter>,
+/// The Pyth Solana receiver program, `rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ`,
+/// which owns `PriceUpdateV2` accounts.
+pub const PYTH_RECEIVER_ID: Pubkey = Pubkey::new_from_array([
+    12, 183, 250, 187, 82, 247, 166, 72, 187, 91, 49, 125, 154, 1, 139, 144, 87, 203, 2, 71, 116,
+    250, 254, 1, 230, 196, 223, 152, 204, 56, 88, 129,
+]);
+
+/// The leading fields of a Pyth `PriceUpdateV2` account, after its discriminator.
+#[derive(AnchorDeserialize)]
+struct PythPriceUpdate {
+    _write_authority: Pubkey,
+    verification_level: PythVerificationLevel,
+    _feed_id: [u8; 32],
+    price: i64,
+    conf: u64,
+    exponent: i32,
+    publish_time: i64,
+}
 
-    #[account(has_one = mint)]
-    pub deposit: Account<'info, TokenAccount>,
-    pub mint: Account<'info, Mint>,
+#[derive(AnchorDeserialize, PartialEq, Eq)]
+enum PythVerificationLevel {
+    Partial { _num_signatures: u8 },
+    Full,
+}
 
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
+impl PriceFeed for PriceFeedKind {
+    fn load(&self, account: &AccountInfo) -> Result<PriceQuote> {
+        match self {
+            PriceFeedKind::Mock => {
+                if account.owner != &crate::ID {
+                    return Err(StrangemoodError::UnexpectedPriceFeed.into());
+                }
+                let feed = MockPriceFeed::try_deserialize(&mut &account.data.borrow()[..])?;
+                Ok(PriceQuote {
+                    price: feed.price,
+                    conf: feed.conf,
+                    expo: feed.expo,
+                    publish_time: feed.publish_time,
+                })
+            }
+            PriceFeedKind::Pyth => {
+                if account.owner != &PYTH_RECEIVER_ID {
+                    return Err(StrangemoodError::UnexpectedPriceFeed.into());
+                }
+                let data = account.data.borrow();
+                if data.len() < 8 || data[..8] != hash(b"account:PriceUpdateV2").to_bytes()[..8] {
+                    return Err(StrangemoodError::UnexpectedPriceFeed.into());
+                }
+                let update = PythPriceUpdate::deserialize(&mut &data[8..])
+                    .map_err(|_| StrangemoodError::UnexpectedPriceFeed)?;
+                if update.verification_level != PythVerificationLevel::Full {
+                    return Err(StrangemoodError::UnexpectedPriceFeed.into());
+                }
+                Ok(PriceQuote {
+                    price: update.price,
+                    conf: update.conf,
+                    expo: update.exponent,
+                    publish_time: update.publish_time,
+                })
+            }
+        }
+    }
 }
 
-// ─── State Accounts ─────────────────────────────────────────────────────────
+impl PriceQuote {
+    pub fn check(&self, config: &OracleConfig, now: i64) -> Result<()> {
+        if self.price <= 0 {
+            return Err(StrangemoodError::InvalidPrice.into());
+        }
+        if self.publish_time > now {
+            return Err(StrangemoodError::PriceFromTheFuture.into());
+        }
+        if now - self.publish_time > config.max_staleness as i64 {
+            return Err(StrangemoodError::StalePrice.into());
+        }
+        if (self.conf as u128) * 10_000 > (self.price as u128) * config.max_confidence_bps as u128 {
+            return Err(StrangemoodError::PriceConfidenceTooWide.into());
+        }
+        Ok(())
+    }
 
-#[account]
-pub struct Receipt {
-    pub is_initialized: bool,
-    pub is_refundable: bool,
-    pub is_cashable: bool,
-    pub listing: Pubkey,
-    pub listing_token_account: Pubkey,
-    pub purchaser: Pubkey,
-    pub cashier: Pubkey,
-    pub escrow: Pubkey,
-    pub quantity: u64,
-    pub price: u64,
-    pub nonce: u128,
+    /// Converts `usd_amount` (scaled by `USD_DECIMALS`) into base units of a
+    /// mint with `decimals`, rounding up in favor of the seller.
+    pub fn convert_usd(&self, usd_amount: u64, decimals: u8) -> Result<u64> {
+        let mut numerator = (usd_amount as u128)
+            .checked_mul(10u128.pow(decimals as u32))
+            .ok_or(StrangemoodError::InvalidPrice)?;
+        let mut denominator = (self.price as u128) * 10u128.pow(USD_DECIMALS as u32);
+        if self.expo < 0 {
+            numerator = numerator
+                .checked_mul(10u128.pow(self.expo.unsigned_abs()))
+                .ok_or(StrangemoodError::InvalidPrice)?;
+        } else {
+            denominator = denominator
+                .checked_mul(10u128.pow(self.expo as u32))
+                .ok_or(StrangemoodError::InvalidPrice)?;
+        }
+        let amount = numerator.div_ceil(denominator);
+        u64::try_from(amount).map_err(|_| StrangemoodError::InvalidPrice.into())
+    }
 }
 
+/// A price account written by its authority, for pegged pricing on localnet.
 #[account]
-pub struct Listing {
-    pub is_initialized: bool,
-    pub is_available: bool,
-    pub charter: Pubkey,
+pub struct MockPriceFeed {
     pub authority: Pubkey,
-    pub payment_deposit: Pubkey,
-    pub vote_deposit: Pubkey,
-    pub price: u64,
-    pub mint: Pubkey,
-    pub uri: String,
-    pub is_refundable: bool,
-    pub is_consumable: bool,
+    pub price: i64,
+    pub conf: u64,
+    pub expo: i32,
+    pub publish_time: i64,
 }
 
 #[account]
@@ -1120,6 +5466,35 @@ pub struct Charter {
     pub mint: Pubkey,
     pub vote_deposit: Pubkey,
     pub uri: String,
+    pub arbiter: Pubkey,
+    pub dispute_period: i64,
+    pub is_dispute_default_refund: bool,
+    pub is_code_fee_charged: bool,
+    pub change_delay: i64,
+    pub change_count: u64,
+    /// Contribution rate caps; 0 is uncapped, as on charters from before caps.
+    pub payment_contribution_cap_bps: u16,
+    pub vote_contribution_cap_bps: u16,
+    pub min_change_delay: i64,
+    pub pending_authority: Pubkey,
+    /// Seconds before a listing's minted votes start vesting.
+    pub vote_vesting_cliff: i64,
+    /// Seconds over which they then vest linearly.
+    pub vote_vesting_duration: i64,
+    /// Share of the previous epoch's expansion rate kept each emission epoch;
+    /// 5_000 halves it every epoch, 10_000 or 0 never decays.
+    pub emission_decay_bps: u16,
+    /// Zero keeps every sale in epoch 0.
+    pub emission_epoch_duration: i64,
+    /// Emission caps; 0 is uncapped, as on charters from before the policy.
+    pub emission_epoch_cap: u64,
+    pub emission_lifetime_cap: u64,
+    pub emission_started_at: i64,
+    /// Share of each sale's minted votes sent to the purchaser.
+    pub buyer_vote_rate_amount: u64,
+    pub buyer_vote_rate_decimals: u8,
+    /// Tighten-only cap on the buyer vote rate; 0 is uncapped.
+    pub buyer_vote_cap_bps: u16,
 }
 
 #[account]
@@ -1130,13 +5505,232 @@ pub struct CharterTreasury {
     pub mint: Pubkey,
     pub expansion_scalar_amount: u64,
     pub expansion_scalar_decimals: u8,
+    pub epoch_duration: i64,
+    /// The most `withdraw_from_treasury` may pay out per epoch.
+    pub epoch_limit: u64,
+    pub epoch_started_at: i64,
+    pub epoch_spent: u64,
+    pub withdrawal_count: u64,
+}
+
+impl CharterTreasury {
+    /// Counts `amount` against the current epoch's limit, starting a new epoch
+    /// if the last one has ended.
+    pub fn spend(&mut self, amount: u64, now: i64) -> Result<()> {
+        check_treasury_limit(self.epoch_duration)?;
+        let elapsed = now.saturating_sub(self.epoch_started_at);
+        if elapsed >= self.epoch_duration {
+            let skipped = (elapsed / self.epoch_duration)
+                .checked_mul(self.epoch_duration)
+                .ok_or(StrangemoodError::InvalidTreasuryLimit)?;
+            self.epoch_started_at += skipped;
+            self.epoch_spent = 0;
+        }
+        let spent = self
+            .epoch_spent
+            .checked_add(amount)
+            .filter(|spent| *spent <= self.epoch_limit)
+            .ok_or(StrangemoodError::TreasuryLimitExceeded)?;
+        self.epoch_spent = spent;
+        self.withdrawal_count += 1;
+        Ok(())
+    }
+}
+
+pub fn check_treasury_limit(epoch_duration: i64) -> Result<()> {
+    if epoch_duration <= 0 {
+        return Err(StrangemoodError::InvalidTreasuryLimit.into());
+    }
+    Ok(())
+}
+
+/// Rates and scalars are `amount / 10^decimals`; beyond this `amount_as_float`
+/// overflows.
+pub const MAX_DECIMALS: u8 = 9;
+
+pub fn check_decimals(decimals: u8) -> Result<()> {
+    if decimals > MAX_DECIMALS {
+        return Err(StrangemoodError::InvalidDecimals.into());
+    }
+    Ok(())
+}
+
+/// Checks that `amount / 10^decimals` is a rate between 0 and `cap_bps / 10_000`.
+pub fn check_rate(amount: u64, decimals: u8, cap_bps: u16) -> Result<()> {
+    check_decimals(decimals)?;
+    if amount as u128 * 10_000 > cap_bps as u128 * 10u128.pow(decimals as u32) {
+        return Err(StrangemoodError::RateOutOfBounds.into());
+    }
+    Ok(())
+}
+
+/// A stored cap in basis points, where 0 means uncapped.
+pub fn cap_or_uncapped(cap_bps: u16) -> u16 {
+    if cap_bps == 0 {
+        10_000
+    } else {
+        cap_bps
+    }
+}
+
+pub fn check_expansion_scalar(amount: u64, decimals: u8) -> Result<()> {
+    check_decimals(decimals)?;
+    if amount == 0 {
+        return Err(StrangemoodError::RateOutOfBounds.into());
+    }
+    Ok(())
 }
 
 pub(crate) fn amount_as_float(amount: u64, decimals: u8) -> f64 {
     amount as f64 / i32::pow(10, decimals.into()) as f64
 }
 
+impl Listing {
+    /// Resolves the price for a payment in `mint` settled into `payment_deposit`.
+    /// Entries in the price table take precedence over the listing's default price.
+    pub fn payment_terms(
+        &self,
+        price_table: Option<&ListingPriceTable>,
+        payment_deposit: Pubkey,
+        mint: Pubkey,
+    ) -> Result<PaymentTerms> {
+        if self.has_price_table && price_table.is_none() {
+            return Err(StrangemoodError::PriceTableRequired.into());
+        }
+        if let Some(entry) = price_table.and_then(|t| t.entries.iter().find(|e| e.mint == mint)) {
+            if entry.payment_deposit != payment_deposit {
+                return Err(StrangemoodError::DepositIsNotFoundInListing.into());
+            }
+            return Ok(PaymentTerms {
+                price: entry.price,
+                oracle: entry.oracle,
+            });
+        }
+        if self.payment_deposit != payment_deposit {
+            return Err(StrangemoodError::DepositIsNotFoundInListing.into());
+        }
+        Ok(PaymentTerms {
+            price: self.price,
+            oracle: None,
+        })
+    }
+
+    /// The restocking fee withheld when `amount` is cancelled at `now`. With a
+    /// decaying fee, it falls linearly to zero over the refund window.
+    pub fn refund_fee(&self, amount: u64, purchased_at: i64, now: i64) -> Result<u64> {
+        let elapsed = now.saturating_sub(purchased_at).max(0);
+        if self.refund_window > 0 && elapsed > self.refund_window {
+            return Err(StrangemoodError::RefundWindowElapsed.into());
+        }
+        let mut fee = amount as u128 * self.refund_fee_bps as u128 / 10_000;
+        if self.is_refund_fee_decaying {
+            fee = fee * (self.refund_window - elapsed) as u128 / self.refund_window as u128;
+        }
+        Ok(fee as u64)
+    }
+}
+
 impl Charter {
+    /// Caps only ever tighten, so publishers can rely on them.
+    pub fn tighten_caps(
+        &mut self,
+        payment_contribution_cap_bps: u16,
+        vote_contribution_cap_bps: u16,
+        buyer_vote_cap_bps: u16,
+        min_change_delay: i64,
+    ) -> Result<()> {
+        let payment_contribution_cap_bps = cap_or_uncapped(payment_contribution_cap_bps);
+        let vote_contribution_cap_bps = cap_or_uncapped(vote_contribution_cap_bps);
+        let buyer_vote_cap_bps = cap_or_uncapped(buyer_vote_cap_bps);
+        if payment_contribution_cap_bps > cap_or_uncapped(self.payment_contribution_cap_bps)
+            || vote_contribution_cap_bps > cap_or_uncapped(self.vote_contribution_cap_bps)
+            || buyer_vote_cap_bps > cap_or_uncapped(self.buyer_vote_cap_bps)
+            || min_change_delay < self.min_change_delay
+        {
+            return Err(StrangemoodError::CapsCannotLoosen.into());
+        }
+        check_rate(
+            self.payment_contribution_rate_amount,
+            self.payment_contribution_rate_decimals,
+            payment_contribution_cap_bps,
+        )?;
+        check_rate(
+            self.vote_contribution_rate_amount,
+            self.vote_contribution_rate_decimals,
+            vote_contribution_cap_bps,
+        )?;
+        check_rate(
+            self.buyer_vote_rate_amount,
+            self.buyer_vote_rate_decimals,
+            buyer_vote_cap_bps,
+        )?;
+        if self.change_delay < min_change_delay {
+            return Err(StrangemoodError::InvalidChangeDelay.into());
+        }
+        self.payment_contribution_cap_bps = payment_contribution_cap_bps;
+        self.vote_contribution_cap_bps = vote_contribution_cap_bps;
+        self.buyer_vote_cap_bps = buyer_vote_cap_bps;
+        self.min_change_delay = min_change_delay;
+        Ok(())
+    }
+
+    pub fn queue_change(
+        &mut self,
+        key: Pubkey,
+        pending: &mut PendingCharterChange,
+        change: CharterChange,
+        payer: Pubkey,
+    ) -> Result<()> {
+        change.validate(self)?;
+        let now = Clock::get()?.unix_timestamp;
+        pending.is_initialized = true;
+        pending.charter = key;
+        pending.id = self.change_count;
+        pending.change = change;
+        pending.payer = payer;
+        pending.queued_at = now;
+        pending.effective_at = now + self.change_delay;
+        self.change_count += 1;
+        Ok(())
+    }
+
+    pub fn emission_epoch(&self, now: i64) -> u64 {
+        if self.emission_epoch_duration == 0 {
+            return 0;
+        }
+        (now - self.emission_started_at).max(0) as u64 / self.emission_epoch_duration as u64
+    }
+
+    /// Switches to epochs of `epoch_duration` without moving the current epoch
+    /// back: the epoch index at `now` stays put and the next one starts at
+    /// most `epoch_duration` from the current epoch's start.
+    pub fn set_emission_schedule(&mut self, epoch_duration: i64, now: i64) -> Result<()> {
+        let epoch = self.emission_epoch(now);
+        if epoch > 0 && epoch_duration == 0 {
+            return Err(StrangemoodError::InvalidEmissionPolicy.into());
+        }
+        let epoch_started_at = if self.emission_epoch_duration == 0 {
+            now
+        } else {
+            self.emission_started_at + epoch as i64 * self.emission_epoch_duration
+        };
+        self.emission_started_at = (epoch as i64)
+            .checked_mul(epoch_duration)
+            .and_then(|elapsed| epoch_started_at.checked_sub(elapsed))
+            .ok_or(StrangemoodError::InvalidEmissionPolicy)?;
+        self.emission_epoch_duration = epoch_duration;
+        Ok(())
+    }
+
+    /// How much of the expansion rate is left in `epoch`.
+    pub fn emission_factor(&self, epoch: u64) -> f64 {
+        let decay_bps = match self.emission_decay_bps {
+            0 => 10_000,
+            decay_bps => decay_bps,
+        };
+        (decay_bps as f64 / 10_000.0).powf(epoch as f64)
+    }
+
     pub fn expansion_rate(&self, scalar_amount: u64, scalar_decimals: u8) -> f64 {
         amount_as_float(self.expansion_rate_amount, self.expansion_rate_decimals)
             * amount_as_float(scalar_amount, scalar_decimals)
@@ -1147,6 +5741,9 @@ impl Charter {
             self.payment_contribution_rate_decimals,
         )
     }
+    pub fn buyer_vote_rate(&self) -> f64 {
+        amount_as_float(self.buyer_vote_rate_amount, self.buyer_vote_rate_decimals)
+    }
     pub fn vote_contribution_rate(&self) -> f64 {
         amount_as_float(
             self.vote_contribution_rate_amount,
@@ -1155,6 +5752,39 @@ impl Charter {
     }
 }
 
+// ─── Events ─────────────────────────────────────────────────────────────────
+
+#[event]
+pub struct LicenseRevoked {
+    pub listing: Pubkey,
+    pub listing_token_account: Pubkey,
+    pub owner: Pubkey,
+    pub authority: Pubkey,
+    pub uri: String,
+}
+
+#[event]
+pub struct LicenseReinstated {
+    pub listing: Pubkey,
+    pub listing_token_account: Pubkey,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct TreasuryWithdrawn {
+    pub charter: Pubkey,
+    pub treasury: Pubkey,
+    pub deposit: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub authority: Pubkey,
+    /// The pending change a governance withdrawal consumed.
+    pub change_id: Option<u64>,
+    pub withdrawal_id: u64,
+    pub epoch_started_at: i64,
+    pub epoch_spent: u64,
+}
+
 #[error_code]
 pub enum StrangemoodError {
     #[msg("MintNotSupported")]
@@ -1192,4 +5822,211 @@ pub enum StrangemoodError {
 
     #[msg("Listing is not consumable")]
     ListingIsNotConsumable,
+
+    #[msg("Price table is full")]
+    PriceTableFull,
+
+    #[msg("Price feed did not match the price table")]
+    UnexpectedPriceFeed,
+
+    #[msg("Price feed returned an invalid price")]
+    InvalidPrice,
+
+    #[msg("Price feed is stale")]
+    StalePrice,
+
+    #[msg("Price feed confidence interval is too wide")]
+    PriceConfidenceTooWide,
+
+    #[msg("Price exceeds the buyer's maximum")]
+    SlippageExceeded,
+
+    #[msg("Campaign account is required for this listing")]
+    CampaignRequired,
+
+    #[msg("Campaign has ended")]
+    CampaignEnded,
+
+    #[msg("Campaign has reached its goal")]
+    CampaignFunded,
+
+    #[msg("Campaign is still in progress")]
+    CampaignInProgress,
+
+    #[msg("Milestone vault is required for this listing")]
+    MilestonesRequired,
+
+    #[msg("Milestones must release exactly 100% in at most 8 tranches")]
+    InvalidMilestones,
+
+    #[msg("Listing revenue is locked in a milestone vault")]
+    MilestonesLocked,
+
+    #[msg("Listing has been abandoned")]
+    MilestonesAbandoned,
+
+    #[msg("Listing has not been abandoned")]
+    MilestonesNotAbandoned,
+
+    #[msg("Invalid quantity")]
+    InvalidQuantity,
+
+    #[msg("Refund fee must be at most 100% and decay over a positive window")]
+    InvalidRefundPolicy,
+
+    #[msg("Refund window has elapsed")]
+    RefundWindowElapsed,
+
+    #[msg("Deposit accounts are required to collect the refund fee")]
+    RefundFeeAccountsRequired,
+
+    #[msg("Receipt is under dispute")]
+    ReceiptDisputed,
+
+    #[msg("Charter has no arbiter")]
+    ArbiterNotSet,
+
+    #[msg("Dispute is awaiting the arbiter")]
+    DisputeInProgress,
+
+    #[msg("Session has expired")]
+    SessionExpired,
+
+    #[msg("Session does not allow this")]
+    SessionScopeExceeded,
+
+    #[msg("Expected an ed25519 signature verification instruction")]
+    MissingSignatureVerification,
+
+    #[msg("Signature verification does not match")]
+    InvalidSignatureVerification,
+
+    #[msg("Voucher has expired")]
+    VoucherExpired,
+
+    #[msg("Code batch size is out of range")]
+    InvalidCodeBatchSize,
+
+    #[msg("Charter requires a fee for code batches")]
+    CodeFeeRequired,
+
+    #[msg("Code is not in this batch")]
+    InvalidCode,
+
+    #[msg("Code has already been redeemed")]
+    CodeAlreadyRedeemed,
+
+    #[msg("Distributor size is out of range")]
+    InvalidDistributorSize,
+
+    #[msg("Claim is not in this distributor")]
+    InvalidClaim,
+
+    #[msg("License has already been claimed")]
+    AlreadyClaimed,
+
+    #[msg("Voting rules are out of range")]
+    InvalidVotingRules,
+
+    #[msg("Voting has closed")]
+    VotingClosed,
+
+    #[msg("Voting is still in progress")]
+    VotingInProgress,
+
+    #[msg("Proposal did not reach quorum")]
+    QuorumNotReached,
+
+    #[msg("Proposal did not reach the approval threshold")]
+    ThresholdNotReached,
+
+    #[msg("Proposal has already been executed")]
+    ProposalExecuted,
+
+    #[msg("Change requires its charter treasury")]
+    TreasuryRequired,
+
+    #[msg("Change requires the charter's governance")]
+    GovernanceRequired,
+
+    #[msg("Change delay must not be negative")]
+    InvalidChangeDelay,
+
+    #[msg("Change is not effective yet")]
+    ChangeNotYetEffective,
+
+    #[msg("Decimals are out of range")]
+    InvalidDecimals,
+
+    #[msg("Rate is out of bounds")]
+    RateOutOfBounds,
+
+    #[msg("Charter caps can only tighten")]
+    CapsCannotLoosen,
+
+    #[msg("Multisig members or threshold are invalid")]
+    InvalidMultisig,
+
+    #[msg("Signer is not a member of the multisig")]
+    NotAMultisigMember,
+
+    #[msg("Multisig members changed since this transaction was proposed")]
+    StaleTransaction,
+
+    #[msg("Transaction has already been executed")]
+    TransactionExecuted,
+
+    #[msg("Unknown listing role")]
+    InvalidRole,
+
+    #[msg("Treasury epoch duration must be positive")]
+    InvalidTreasuryLimit,
+
+    #[msg("Withdrawal exceeds the treasury's limit for this epoch")]
+    TreasuryLimitExceeded,
+
+    #[msg("Pending change does not match this instruction")]
+    UnexpectedCharterChange,
+
+    #[msg("Vesting cliff and duration must not be negative")]
+    InvalidVestingSchedule,
+
+    #[msg("Emission decay or epoch duration is out of range")]
+    InvalidEmissionPolicy,
+
+    #[msg("Purchaser's vote deposit is required for the buyer's share")]
+    PurchaserVoteDepositRequired,
+
+    #[msg("Listing prices through its price table, which is missing")]
+    PriceTableRequired,
+
+    #[msg("Price feed was published after the current time")]
+    PriceFromTheFuture,
+
+    #[msg("Listing has receipts that are still open")]
+    ListingHasOpenReceipts,
+
+    #[msg("Receipt is not counted in the campaign")]
+    UnbackedReceipt,
+
+    #[msg("Purchaser's milestone backing is required")]
+    MilestoneBackingRequired,
+
+    #[msg("Receipt has already been disputed")]
+    ReceiptAlreadyDisputed,
+
+    #[msg("License is not revoked")]
+    LicenseNotRevoked,
+
+    #[msg("Charter change is already effective")]
+    ChangeAlreadyEffective,
+
+    #[msg("Dispute period must be positive")]
+    InvalidDisputePeriod,
+
+    #[msg("Listing has open disputes")]
+    ListingHasOpenDisputes,
+
+    #[msg("Nonce account passed without a nonce")]
+    UnexpectedNonceAccount,
 }
@@ -1,15 +1,51 @@
 const anchor = require("@coral-xyz/anchor");
-const { PublicKey, Keypair, SystemProgram, SYSVAR_RENT_PUBKEY } = require("@solana/web3.js");
+const {
+  PublicKey,
+  Keypair,
+  SystemProgram,
+  SYSVAR_RENT_PUBKEY,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+  Ed25519Program,
+  ComputeBudgetProgram,
+  Transaction,
+} = require("@solana/web3.js");
 const {
   TOKEN_PROGRAM_ID,
   createMint,
   createAccount,
   mintTo,
   getAccount,
+  setAuthority,
+  AuthorityType,
 } = require("@solana/spl-token");
 
+const crypto = require("crypto");
+
 const CONFIRM_OPTS = { commitment: "confirmed" };
 
+const sha256 = (...parts) => crypto.createHash("sha256").update(Buffer.concat(parts)).digest();
+const hashPair = (a, b) => (Buffer.compare(a, b) <= 0 ? sha256(a, b) : sha256(b, a));
+
+// Builds a sorted-pair sha256 Merkle tree and returns its root and a proof per leaf.
+function merkleTree(leaves) {
+  let level = leaves;
+  const proofs = leaves.map(() => []);
+  let positions = leaves.map((_, i) => i);
+  while (level.length > 1) {
+    const next = [];
+    for (let i = 0; i < level.length; i += 2) {
+      next.push(i + 1 < level.length ? hashPair(level[i], level[i + 1]) : level[i]);
+    }
+    positions = positions.map((pos, leaf) => {
+      const sibling = pos ^ 1;
+      if (sibling < level.length) proofs[leaf].push(level[sibling]);
+      return pos >> 1;
+    });
+    level = next;
+  }
+  return { root: level[0], proofs };
+}
+
 describe("strangemood-revival", () => {
   const provider = anchor.AnchorProvider.env();
   anchor.setProvider(provider);
@@ -28,6 +64,295 @@ describe("strangemood-revival", () => {
   let listingPaymentDeposit;
   let listingVoteDeposit;
 
+  // Creates a standalone charter, for tests that hand its authority away.
+  async function createCharter() {
+    const mint = await createMint(
+      provider.connection,
+      wallet.payer,
+      wallet.publicKey,
+      wallet.publicKey,
+      0,
+      Keypair.generate(),
+      CONFIRM_OPTS
+    );
+    const voteDeposit = await createAccount(
+      provider.connection,
+      wallet.payer,
+      mint,
+      wallet.publicKey,
+      Keypair.generate(),
+      CONFIRM_OPTS
+    );
+    const [charter, charterBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("charter"), mint.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .initCharter(charterBump, new anchor.BN(1), 0, new anchor.BN(10), 2, new anchor.BN(10), 2, "")
+      .accounts({
+        charter,
+        mint,
+        authority: wallet.publicKey,
+        voteDeposit,
+        user: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+    return { charter, mint, voteDeposit };
+  }
+
+  // Finds the PDA for the charter's next queued change.
+  async function nextPendingChange(charter) {
+    const { changeCount } = await program.account.charter.fetch(charter);
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("pending"), charter.toBuffer(), changeCount.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+  }
+
+  // Queues a charter change with `queue(pending, pendingBump)` and applies it
+  // straight away, which works while the charter has no change delay.
+  async function changeCharter(charter, queue, { treasury = null, governance = null } = {}) {
+    const [pending, pendingBump] = await nextPendingChange(charter);
+    await queue(pending, pendingBump).rpc();
+    await program.methods
+      .applyCharterChange()
+      .accounts({ pending, charter, treasury, governance, payer: wallet.publicKey })
+      .rpc();
+  }
+
+  // Lists a fresh game against the shared charter and treasury.
+  async function listGame({ price = 1_000_000, refundable = true, consumable = false } = {}) {
+    const mint = Keypair.generate();
+    const [mintAuthority, mintBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("mint"), mint.publicKey.toBuffer()],
+      program.programId
+    );
+    const [listing, listingBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("listing"), mint.publicKey.toBuffer()],
+      program.programId
+    );
+    const paymentDeposit = await createAccount(
+      provider.connection,
+      wallet.payer,
+      paymentMint,
+      wallet.publicKey,
+      Keypair.generate(),
+      CONFIRM_OPTS
+    );
+    const voteDeposit = await createAccount(
+      provider.connection,
+      wallet.payer,
+      charterMint,
+      wallet.publicKey,
+      Keypair.generate(),
+      CONFIRM_OPTS
+    );
+
+    await program.methods
+      .initListing(
+        mintBump,
+        listingBump,
+        0,
+        new anchor.BN(price),
+        refundable,
+        consumable,
+        true,
+        "https://strangemood-revival.dev/games/test"
+      )
+      .accounts({
+        listing,
+        mintAuthorityPda: mintAuthority,
+        mint: mint.publicKey,
+        paymentDeposit,
+        voteDeposit,
+        charter: charterPda,
+        charterTreasury: treasuryPda,
+        rent: SYSVAR_RENT_PUBKEY,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        user: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([mint])
+      .rpc();
+
+    return { listing, mint: mint.publicKey, mintAuthority, mintBump, paymentDeposit, voteDeposit };
+  }
+
+  // Buys `quantity` licenses of a game listed with `listGame`, paying from a freshly funded account.
+  // Pass a funded `buyer` keypair to purchase as someone other than the listing authority.
+  async function purchaseGame(
+    game,
+    { quantity = 1, cashier = Keypair.generate().publicKey, voucher = null, buyer = null, voucherNonce = null } = {}
+  ) {
+    const purchaser = buyer ? buyer.publicKey : wallet.publicKey;
+    const purchaseTokenAccount = await createAccount(
+      provider.connection,
+      wallet.payer,
+      paymentMint,
+      purchaser,
+      Keypair.generate(),
+      CONFIRM_OPTS
+    );
+    await mintTo(
+      provider.connection,
+      wallet.payer,
+      paymentMint,
+      purchaseTokenAccount,
+      wallet.publicKey,
+      100_000_000,
+      [],
+      CONFIRM_OPTS
+    );
+    const listingTokenAccount = await createAccount(
+      provider.connection,
+      wallet.payer,
+      game.mint,
+      purchaser,
+      Keypair.generate(),
+      CONFIRM_OPTS
+    );
+
+    const escrow = Keypair.generate();
+    const [escrowAuthority, escrowBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), escrow.publicKey.toBuffer()],
+      program.programId
+    );
+    const nonce = new anchor.BN(Date.now()).muln(1000).addn(Math.floor(Math.random() * 1000));
+    const [receipt, receiptBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("receipt"), nonce.toArrayLike(Buffer, "le", 16)],
+      program.programId
+    );
+
+    // A voucher is { price, expiresAt, nonce, signer }, signed here by the listing's pricing key.
+    const voucherArgs = voucher && { price: voucher.price, expiresAt: voucher.expiresAt, nonce: voucher.nonce };
+    const preInstructions = [];
+    if (voucher) {
+      preInstructions.push(
+        Ed25519Program.createInstructionWithPrivateKey({
+          privateKey: voucher.signer.secretKey,
+          message: Buffer.concat([
+            Buffer.from("strangemood:voucher"),
+            game.listing.toBuffer(),
+            purchaser.toBuffer(),
+            paymentMint.toBuffer(),
+            voucher.price.toArrayLike(Buffer, "le", 8),
+            voucher.expiresAt.toTwos(64).toArrayLike(Buffer, "le", 8),
+            voucher.nonce.toArrayLike(Buffer, "le", 8),
+          ]),
+        })
+      );
+      [voucherNonce] = PublicKey.findProgramAddressSync(
+        [Buffer.from("voucher"), game.listing.toBuffer(), voucher.nonce.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+    }
+
+    await program.methods
+      .purchase(
+        nonce,
+        receiptBump,
+        game.mintBump,
+        escrowBump,
+        new anchor.BN(quantity),
+        new anchor.BN("18446744073709551615"),
+        voucherArgs
+      )
+      .accounts({
+        purchaseTokenAccount,
+        listing: game.listing,
+        priceTable: null,
+        priceFeed: null,
+        campaign: null,
+        voucherNonce,
+        instructions: voucher ? SYSVAR_INSTRUCTIONS_PUBKEY : null,
+        listingPaymentDeposit: game.paymentDeposit,
+        listingPaymentDepositMint: paymentMint,
+        cashier,
+        listingTokenAccount,
+        listingMint: game.mint,
+        listingMintAuthority: game.mintAuthority,
+        receipt,
+        escrow: escrow.publicKey,
+        escrowAuthority,
+        user: purchaser,
+        systemProgram: SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        rent: SYSVAR_RENT_PUBKEY,
+      })
+      .preInstructions(preInstructions)
+      .signers(buyer ? [escrow, buyer] : [escrow])
+      .rpc();
+
+    return { receipt, escrow: escrow.publicKey, escrowAuthority, escrowBump, purchaseTokenAccount, listingTokenAccount };
+  }
+
+  // Cashes a purchase made with `purchaseGame(game, { cashier: wallet.publicKey })`.
+  // Pass a `cashier` keypair and its `role` to cash as a delegated cashier.
+  async function cashGame(game, purchase, { purchaserVoteDeposit = null, milestones = null, cashier = null, role = null } = {}) {
+    const [charterMintAuthority, charterMintBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("mint"), charterMint.toBuffer()],
+      program.programId
+    );
+    const [vesting] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vesting"), game.listing.toBuffer(), charterMint.toBuffer()],
+      program.programId
+    );
+    const [vestingVault] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), vesting.toBuffer()],
+      program.programId
+    );
+    const [emissions] = PublicKey.findProgramAddressSync(
+      [Buffer.from("emissions"), charterPda.toBuffer()],
+      program.programId
+    );
+    const { purchaser } = await program.account.receipt.fetch(purchase.receipt);
+    const backing = milestones
+      ? PublicKey.findProgramAddressSync(
+          [Buffer.from("backing"), game.listing.toBuffer(), purchaser.toBuffer()],
+          program.programId
+        )[0]
+      : null;
+    const [purchaserRole] = PublicKey.findProgramAddressSync(
+      [Buffer.from("role"), game.listing.toBuffer(), purchaser.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .cash(game.mintBump, charterMintBump, purchase.escrowBump)
+      .accounts({
+        cashier: cashier ? cashier.publicKey : wallet.publicKey,
+        role,
+        receipt: purchase.receipt,
+        escrow: purchase.escrow,
+        escrowAuthority: purchase.escrowAuthority,
+        listingTokenAccount: purchase.listingTokenAccount,
+        listingsPaymentDeposit: game.paymentDeposit,
+        vesting,
+        vestingVault,
+        listing: game.listing,
+        priceTable: null,
+        campaign: null,
+        milestones,
+        backing,
+        listingMint: game.mint,
+        listingMintAuthority: game.mintAuthority,
+        charterTreasury: treasuryPda,
+        charterTreasuryDeposit: treasuryDeposit,
+        charterVoteDeposit,
+        purchaserVoteDeposit,
+        purchaserRole,
+        charterMint,
+        charterMintAuthority,
+        charter: charterPda,
+        emissions,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers(cashier ? [cashier] : [])
+      .rpc();
+    return { vesting, vestingVault, emissions, backing };
+  }
+
   it("Initializes a Charter (marketplace DAO)", async () => {
     charterMint = await createMint(
       provider.connection,
@@ -55,6 +380,22 @@ describe("strangemood-revival", () => {
     );
     charterPda = _charterPda;
 
+    // `cash` mints votes through the charter mint's PDA authority.
+    const [charterMintAuthority] = PublicKey.findProgramAddressSync(
+      [Buffer.from("mint"), charterMint.toBuffer()],
+      program.programId
+    );
+    await setAuthority(
+      provider.connection,
+      wallet.payer,
+      charterMint,
+      wallet.publicKey,
+      AuthorityType.MintTokens,
+      charterMintAuthority,
+      [],
+      CONFIRM_OPTS
+    );
+
     await program.methods
       .initCharter(
         charterBump,
@@ -94,16 +435,6 @@ describe("strangemood-revival", () => {
       CONFIRM_OPTS
     );
 
-    const treasuryDepositKp = Keypair.generate();
-    treasuryDeposit = await createAccount(
-      provider.connection,
-      wallet.payer,
-      paymentMint,
-      wallet.publicKey,
-      treasuryDepositKp,
-      CONFIRM_OPTS
-    );
-
     const [_treasuryPda, treasuryBump] = PublicKey.findProgramAddressSync(
       [
         Buffer.from("treasury"),
@@ -113,12 +444,18 @@ describe("strangemood-revival", () => {
       program.programId
     );
     treasuryPda = _treasuryPda;
+    [treasuryDeposit] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), treasuryPda.toBuffer()],
+      program.programId
+    );
 
     await program.methods
       .initCharterTreasury(
         treasuryBump,
         new anchor.BN(1),
-        0
+        0,
+        new anchor.BN(86_400),
+        new anchor.BN(1_000_000)
       )
       .accounts({
         treasury: treasuryPda,
@@ -127,6 +464,8 @@ describe("strangemood-revival", () => {
         mint: paymentMint,
         authority: wallet.publicKey,
         systemProgram: SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        rent: SYSVAR_RENT_PUBKEY,
       })
       .rpc();
 
@@ -269,11 +608,18 @@ describe("strangemood-revival", () => {
         receiptBump,
         mintBump,
         escrowBump,
-        new anchor.BN(1)
+        new anchor.BN(1),
+        new anchor.BN(1_000_000),
+        null
       )
       .accounts({
         purchaseTokenAccount: buyerPaymentAccount,
         listing: listingPda,
+        priceTable: null,
+        priceFeed: null,
+        campaign: null,
+        voucherNonce: null,
+        instructions: null,
         listingPaymentDeposit: listingPaymentDeposit,
         listingPaymentDepositMint: paymentMint,
         cashier: cashier.publicKey,
@@ -317,6 +663,7 @@ describe("strangemood-revival", () => {
       .setListingPrice(new anchor.BN(2_000_000))
       .accounts({
         listing: listingPda,
+        role: null,
         user: wallet.publicKey,
         systemProgram: SystemProgram.programId,
       })
@@ -332,6 +679,7 @@ describe("strangemood-revival", () => {
       .setListingAvailability(false)
       .accounts({
         listing: listingPda,
+        role: null,
         user: wallet.publicKey,
         systemProgram: SystemProgram.programId,
       })
@@ -345,6 +693,7 @@ describe("strangemood-revival", () => {
       .setListingAvailability(true)
       .accounts({
         listing: listingPda,
+        role: null,
         user: wallet.publicKey,
         systemProgram: SystemProgram.programId,
       })
@@ -355,21 +704,1678 @@ describe("strangemood-revival", () => {
     console.log("  Re-listed game");
   });
 
-  it("Updates charter contribution rates", async () => {
+  it("Prices a listing per payment mint", async () => {
+    const [priceTablePda, priceTableBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("prices"), listingPda.toBuffer()],
+      program.programId
+    );
+
     await program.methods
-      .setCharterContributionRate(
-        new anchor.BN(15),
-        2,
-        new anchor.BN(15),
-        2
-      )
+      .initListingPriceTable(priceTableBump)
+      .accounts({
+        priceTable: priceTablePda,
+        listing: listingPda,
+        user: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const altDepositKp = Keypair.generate();
+    const altDeposit = await createAccount(
+      provider.connection,
+      wallet.payer,
+      paymentMint,
+      wallet.publicKey,
+      altDepositKp,
+      CONFIRM_OPTS
+    );
+
+    await program.methods
+      .setListingPriceTableEntry(new anchor.BN(750_000), null)
+      .accounts({
+        priceTable: priceTablePda,
+        listing: listingPda,
+        charter: charterPda,
+        charterTreasury: treasuryPda,
+        paymentDeposit: altDeposit,
+        user: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const priceTable = await program.account.listingPriceTable.fetch(priceTablePda);
+    console.log("  Price entries:", priceTable.entries.length);
+    assert(priceTable.entries.length === 1);
+    assert(priceTable.entries[0].mint.equals(paymentMint));
+    assert(priceTable.entries[0].price.toNumber() === 750_000);
+    assert(priceTable.entries[0].paymentDeposit.equals(altDeposit));
+  });
+
+  it("Pegs a listing price to a USD price feed", async () => {
+    const priceFeed = Keypair.generate();
+    await program.methods
+      .initMockPriceFeed(new anchor.BN(150_000_000), new anchor.BN(10_000), -8)
+      .accounts({
+        priceFeed: priceFeed.publicKey,
+        authority: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([priceFeed])
+      .rpc();
+
+    const [priceTablePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("prices"), listingPda.toBuffer()],
+      program.programId
+    );
+    const priceTable = await program.account.listingPriceTable.fetch(priceTablePda);
+
+    await program.methods
+      .setListingPriceTableEntry(new anchor.BN(20_000_000), {
+        kind: { mock: {} },
+        priceFeed: priceFeed.publicKey,
+        maxStaleness: 60,
+        maxConfidenceBps: 100,
+      })
       .accounts({
+        priceTable: priceTablePda,
+        listing: listingPda,
         charter: charterPda,
+        charterTreasury: treasuryPda,
+        paymentDeposit: priceTable.entries[0].paymentDeposit,
+        user: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const pegged = await program.account.listingPriceTable.fetch(priceTablePda);
+    console.log("  USD price:", pegged.entries[0].price.toNumber() / 1e6);
+    assert(pegged.entries.length === 1);
+    assert(pegged.entries[0].oracle.priceFeed.equals(priceFeed.publicKey));
+  });
+
+  it("Opens a crowdfunding campaign", async () => {
+    const game = await listGame({ refundable: false });
+    const [campaignPda, campaignBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("campaign"), game.listing.toBuffer()],
+      program.programId
+    );
+    const deadline = Math.floor(Date.now() / 1000) + 3600;
+
+    await program.methods
+      .initCampaign(campaignBump, new anchor.BN(50_000_000), new anchor.BN(deadline))
+      .accounts({
+        campaign: campaignPda,
+        listing: game.listing,
+        mint: paymentMint,
+        user: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const campaign = await program.account.campaign.fetch(campaignPda);
+    const listing = await program.account.listing.fetch(game.listing);
+    console.log("  Goal:", campaign.goal.toNumber() / 1e6, "USDC");
+    assert(listing.isCampaign === true);
+    assert(campaign.raised.toNumber() === 0);
+    assert(campaign.isFunded === false);
+  });
+
+  it("Locks early-access revenue behind milestones", async () => {
+    const game = await listGame();
+    const [milestonesPda, milestonesBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("milestones"), game.listing.toBuffer()],
+      program.programId
+    );
+    const [vaultPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("milestone_vault"), game.listing.toBuffer()],
+      program.programId
+    );
+
+    await program.methods
+      .initMilestones(milestonesBump, [2_500, 2_500, 5_000])
+      .accounts({
+        milestones: milestonesPda,
+        vault: vaultPda,
+        listing: game.listing,
+        paymentDeposit: game.paymentDeposit,
+        mint: paymentMint,
         user: wallet.publicKey,
+        rent: SYSVAR_RENT_PUBKEY,
+        tokenProgram: TOKEN_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
       })
       .rpc();
 
+    const milestones = await program.account.milestoneVault.fetch(milestonesPda);
+    const listing = await program.account.listing.fetch(game.listing);
+    console.log("  Milestones:", milestones.milestones.length);
+    assert(listing.isMilestoneFunded === true);
+    assert(listing.paymentDeposit.equals(vaultPda));
+    assert(milestones.beneficiary.equals(game.paymentDeposit));
+
+    await program.methods
+      .approveMilestone(milestonesBump, 0)
+      .accounts({
+        milestones: milestonesPda,
+        vault: vaultPda,
+        beneficiary: game.paymentDeposit,
+        listing: game.listing,
+        charter: charterPda,
+        pending: null,
+        payer: null,
+        user: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+
+    const approved = await program.account.milestoneVault.fetch(milestonesPda);
+    assert(approved.milestones[0].isApproved === true);
+    assert(approved.milestones[1].isApproved === false);
+
+    // Only licenses cashed into the vault count towards a refund.
+    const funded = { ...game, paymentDeposit: vaultPda };
+    const purchase = await purchaseGame(funded, { quantity: 2, cashier: wallet.publicKey });
+    const { backing } = await cashGame(funded, purchase, { milestones: milestonesPda });
+    const backed = await program.account.milestoneBacking.fetch(backing);
+    assert(backed.purchaser.equals(wallet.publicKey));
+    assert(backed.licenses.toNumber() === 2);
+
+    await program.methods
+      .abandonMilestones()
+      .accounts({
+        milestones: milestonesPda,
+        vault: vaultPda,
+        listing: game.listing,
+        charter: charterPda,
+        pending: null,
+        payer: null,
+        user: wallet.publicKey,
+      })
+      .rpc();
+
+    const claim = (quantity) =>
+      program.methods
+        .claimMilestoneRefund(milestonesBump, game.mintBump, new anchor.BN(quantity))
+        .accounts({
+          milestones: milestonesPda,
+          backing,
+          vault: vaultPda,
+          listing: game.listing,
+          listingMint: game.mint,
+          listingMintAuthority: game.mintAuthority,
+          listingTokenAccount: purchase.listingTokenAccount,
+          returnDeposit: purchase.purchaseTokenAccount,
+          holder: wallet.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+    await claim(1);
+    const licenses = await getAccount(provider.connection, purchase.listingTokenAccount);
+    assert(Number(licenses.amount) === 1);
+    assert(licenses.isFrozen);
+
+    let overclaimed = false;
+    try {
+      await claim(2);
+      overclaimed = true;
+    } catch (e) {}
+    assert(!overclaimed);
+  });
+
+  it("Refunds a purchase on behalf of the publisher", async () => {
+    const game = await listGame({ refundable: false });
+    const purchase = await purchaseGame(game);
+
+    await program.methods
+      .refund(game.mintBump, purchase.escrowBump)
+      .accounts({
+        authority: wallet.publicKey,
+        role: null,
+        purchaser: wallet.publicKey,
+        returnDeposit: purchase.purchaseTokenAccount,
+        receipt: purchase.receipt,
+        escrow: purchase.escrow,
+        escrowAuthority: purchase.escrowAuthority,
+        listingTokenAccount: purchase.listingTokenAccount,
+        listing: game.listing,
+        charter: charterPda,
+        campaign: null,
+        listingMint: game.mint,
+        listingMintAuthority: game.mintAuthority,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const refunded = await getAccount(provider.connection, purchase.purchaseTokenAccount);
+    console.log("  Refunded balance:", Number(refunded.amount) / 1e6, "USDC");
+    assert(Number(refunded.amount) === 100_000_000);
+    assert((await provider.connection.getAccountInfo(purchase.receipt)) === null);
+  });
+
+  it("Refunds a refundable purchase and burns its license", async () => {
+    const game = await listGame({ refundable: true });
+    const purchase = await purchaseGame(game, { quantity: 2 });
+    const before = await getAccount(provider.connection, purchase.listingTokenAccount);
+    assert(before.isFrozen);
+
+    await program.methods
+      .refund(game.mintBump, purchase.escrowBump)
+      .accounts({
+        authority: wallet.publicKey,
+        role: null,
+        purchaser: wallet.publicKey,
+        returnDeposit: purchase.purchaseTokenAccount,
+        receipt: purchase.receipt,
+        escrow: purchase.escrow,
+        escrowAuthority: purchase.escrowAuthority,
+        listingTokenAccount: purchase.listingTokenAccount,
+        listing: game.listing,
+        charter: charterPda,
+        campaign: null,
+        listingMint: game.mint,
+        listingMintAuthority: game.mintAuthority,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const license = await getAccount(provider.connection, purchase.listingTokenAccount);
+    const refunded = await getAccount(provider.connection, purchase.purchaseTokenAccount);
+    console.log("  Licenses left after refund:", Number(license.amount));
+    assert(Number(license.amount) === 0);
+    assert(Number(refunded.amount) === 100_000_000);
+    assert((await provider.connection.getAccountInfo(purchase.receipt)) === null);
+  });
+
+  it("Partially cancels a purchase less the restocking fee", async () => {
+    const game = await listGame({ refundable: false });
+    await program.methods
+      .setListingRefundPolicy(1_000, new anchor.BN(0), false)
+      .accounts({
+        listing: game.listing,
+        role: null,
+        user: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const purchase = await purchaseGame(game, { quantity: 3 });
+    const [, listingBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("listing"), game.mint.toBuffer()],
+      program.programId
+    );
+
+    await program.methods
+      .cancel(listingBump, game.mintBump, purchase.escrowBump, new anchor.BN(1))
+      .accounts({
+        purchaser: wallet.publicKey,
+        returnDeposit: purchase.purchaseTokenAccount,
+        receipt: purchase.receipt,
+        escrow: purchase.escrow,
+        escrowAuthority: purchase.escrowAuthority,
+        listingTokenAccount: purchase.listingTokenAccount,
+        listing: game.listing,
+        campaign: null,
+        listingMint: game.mint,
+        listingMintAuthority: game.mintAuthority,
+        priceTable: null,
+        listingsPaymentDeposit: game.paymentDeposit,
+        charter: charterPda,
+        charterTreasury: treasuryPda,
+        charterTreasuryDeposit: treasuryDeposit,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const receipt = await program.account.receipt.fetch(purchase.receipt);
+    const returned = await getAccount(provider.connection, purchase.purchaseTokenAccount);
+    const escrow = await getAccount(provider.connection, purchase.escrow);
+    console.log("  Remaining quantity:", receipt.quantity.toNumber());
+    assert(receipt.quantity.toNumber() === 2);
+    assert(Number(returned.amount) === 100_000_000 - 3_000_000 + 900_000);
+    assert(Number(escrow.amount) === 2_000_000);
+  });
+
+  it("Cancels a refundable purchase in part, then in full", async () => {
+    const game = await listGame({ refundable: true });
+    const purchase = await purchaseGame(game, { quantity: 3 });
+    const [, listingBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("listing"), game.mint.toBuffer()],
+      program.programId
+    );
+    const cancel = (quantity) =>
+      program.methods
+        .cancel(listingBump, game.mintBump, purchase.escrowBump, new anchor.BN(quantity))
+        .accounts({
+          purchaser: wallet.publicKey,
+          returnDeposit: purchase.purchaseTokenAccount,
+          receipt: purchase.receipt,
+          escrow: purchase.escrow,
+          escrowAuthority: purchase.escrowAuthority,
+          listingTokenAccount: purchase.listingTokenAccount,
+          listing: game.listing,
+          campaign: null,
+          listingMint: game.mint,
+          listingMintAuthority: game.mintAuthority,
+          priceTable: null,
+          listingsPaymentDeposit: game.paymentDeposit,
+          charter: charterPda,
+          charterTreasury: treasuryPda,
+          charterTreasuryDeposit: treasuryDeposit,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+    // What's left after a partial cancel stays frozen.
+    await cancel(1);
+    let license = await getAccount(provider.connection, purchase.listingTokenAccount);
+    console.log("  Licenses left after partial cancel:", Number(license.amount));
+    assert(Number(license.amount) === 2);
+    assert(license.isFrozen);
+
+    await cancel(2);
+    license = await getAccount(provider.connection, purchase.listingTokenAccount);
+    const returned = await getAccount(provider.connection, purchase.purchaseTokenAccount);
+    assert(Number(license.amount) === 0);
+    assert(Number(returned.amount) === 100_000_000);
+    assert((await provider.connection.getAccountInfo(purchase.receipt)) === null);
+  });
+
+  it("Rules a dispute in the purchaser's favor", async () => {
+    await changeCharter(charterPda, (pending, pendingBump) =>
+      program.methods.setCharterArbiter(pendingBump).accounts({
+        charter: charterPda,
+        pending,
+        arbiter: wallet.publicKey,
+        user: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+    );
+    await changeCharter(charterPda, (pending, pendingBump) =>
+      program.methods.setCharterDisputePolicy(pendingBump, new anchor.BN(7 * 24 * 3600), true).accounts({
+        charter: charterPda,
+        pending,
+        user: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+    );
+
+    const game = await listGame({ refundable: false });
+    const purchase = await purchaseGame(game);
+    const [disputePda, disputeBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("dispute"), purchase.receipt.toBuffer()],
+      program.programId
+    );
+
+    await program.methods
+      .openDispute(disputeBump, "https://strangemood-revival.dev/disputes/crashes-on-launch")
+      .accounts({
+        dispute: disputePda,
+        receipt: purchase.receipt,
+        listing: game.listing,
+        charter: charterPda,
+        purchaser: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const receipt = await program.account.receipt.fetch(purchase.receipt);
+    assert(receipt.isDisputed === true);
+
+    await program.methods
+      .resolveDispute(game.mintBump, purchase.escrowBump, { refund: {} })
+      .accounts({
+        authority: wallet.publicKey,
+        dispute: disputePda,
+        purchaser: wallet.publicKey,
+        returnDeposit: purchase.purchaseTokenAccount,
+        receipt: purchase.receipt,
+        escrow: purchase.escrow,
+        escrowAuthority: purchase.escrowAuthority,
+        listingTokenAccount: purchase.listingTokenAccount,
+        listing: game.listing,
+        charter: charterPda,
+        campaign: null,
+        listingMint: game.mint,
+        listingMintAuthority: game.mintAuthority,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const refunded = await getAccount(provider.connection, purchase.purchaseTokenAccount);
+    console.log("  Refunded by ruling:", Number(refunded.amount) / 1e6, "USDC");
+    assert(Number(refunded.amount) === 100_000_000);
+    assert((await provider.connection.getAccountInfo(disputePda)) === null);
+  });
+
+  it("Refuses a second dispute after a release ruling", async () => {
+    const game = await listGame({ refundable: false });
+    const purchase = await purchaseGame(game);
+    const [disputePda, disputeBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("dispute"), purchase.receipt.toBuffer()],
+      program.programId
+    );
+    const openDispute = () =>
+      program.methods
+        .openDispute(disputeBump, "https://strangemood-revival.dev/disputes/crashes-on-launch")
+        .accounts({
+          dispute: disputePda,
+          receipt: purchase.receipt,
+          listing: game.listing,
+          charter: charterPda,
+          purchaser: wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+    await openDispute();
+    await program.methods
+      .resolveDispute(game.mintBump, purchase.escrowBump, { release: {} })
+      .accounts({
+        authority: wallet.publicKey,
+        dispute: disputePda,
+        purchaser: wallet.publicKey,
+        returnDeposit: purchase.purchaseTokenAccount,
+        receipt: purchase.receipt,
+        escrow: purchase.escrow,
+        escrowAuthority: purchase.escrowAuthority,
+        listingTokenAccount: purchase.listingTokenAccount,
+        listing: game.listing,
+        charter: charterPda,
+        campaign: null,
+        listingMint: game.mint,
+        listingMintAuthority: game.mintAuthority,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const receipt = await program.account.receipt.fetch(purchase.receipt);
+    assert(receipt.isDisputed === false);
+    assert(receipt.wasDisputed === true);
+
+    let reopened = false;
+    try {
+      await openDispute();
+      reopened = true;
+    } catch (e) {
+      assert(e.error.errorCode.code === "ReceiptAlreadyDisputed");
+    }
+    assert(!reopened);
+  });
+
+  it("Refunds a disputed refundable purchase and pins its listing's charter", async () => {
+    const game = await listGame({ refundable: true });
+    const purchase = await purchaseGame(game);
+    const [disputePda, disputeBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("dispute"), purchase.receipt.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .openDispute(disputeBump, "https://strangemood-revival.dev/disputes/crashes-on-launch")
+      .accounts({
+        dispute: disputePda,
+        receipt: purchase.receipt,
+        listing: game.listing,
+        charter: charterPda,
+        purchaser: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    // The dispute belongs to this charter's arbiter, so the listing can't leave it.
+    const { charter: otherCharter } = await createCharter();
+    const moveListing = () =>
+      program.methods
+        .setListingCharter()
+        .accounts({ listing: game.listing, charter: otherCharter, user: wallet.publicKey, systemProgram: SystemProgram.programId })
+        .rpc();
+    let moved = false;
+    try {
+      await moveListing();
+      moved = true;
+    } catch (e) {
+      assert(e.error.errorCode.code === "ListingHasOpenDisputes");
+    }
+    assert(!moved);
+
+    await program.methods
+      .resolveDispute(game.mintBump, purchase.escrowBump, { refund: {} })
+      .accounts({
+        authority: wallet.publicKey,
+        dispute: disputePda,
+        purchaser: wallet.publicKey,
+        returnDeposit: purchase.purchaseTokenAccount,
+        receipt: purchase.receipt,
+        escrow: purchase.escrow,
+        escrowAuthority: purchase.escrowAuthority,
+        listingTokenAccount: purchase.listingTokenAccount,
+        listing: game.listing,
+        charter: charterPda,
+        campaign: null,
+        listingMint: game.mint,
+        listingMintAuthority: game.mintAuthority,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const license = await getAccount(provider.connection, purchase.listingTokenAccount);
+    const refunded = await getAccount(provider.connection, purchase.purchaseTokenAccount);
+    console.log("  Licenses left after ruling:", Number(license.amount));
+    assert(Number(license.amount) === 0);
+    assert(Number(refunded.amount) === 100_000_000);
+
+    await moveListing();
+    assert((await program.account.listing.fetch(game.listing)).charter.equals(otherCharter));
+  });
+
+  it("Revokes and reinstates a license", async () => {
+    const game = await listGame();
+    const purchase = await purchaseGame(game);
+    const [revocationPda, revocationBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("revocation"), purchase.listingTokenAccount.toBuffer()],
+      program.programId
+    );
+
+    await program.methods
+      .revokeLicense(revocationBump, game.mintBump, "https://strangemood-revival.dev/revocations/chargeback")
+      .accounts({
+        revocation: revocationPda,
+        listingTokenAccount: purchase.listingTokenAccount,
+        listing: game.listing,
+        charter: charterPda,
+        listingMint: game.mint,
+        listingMintAuthority: game.mintAuthority,
+        authority: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    let revocation = await program.account.licenseRevocation.fetch(revocationPda);
+    console.log("  Revoked:", revocation.isRevoked);
+    assert(revocation.isRevoked === true);
+
+    await program.methods
+      .reinstateLicense()
+      .accounts({
+        revocation: revocationPda,
+        listing: game.listing,
+        charter: charterPda,
+        authority: wallet.publicKey,
+      })
+      .rpc();
+
+    revocation = await program.account.licenseRevocation.fetch(revocationPda);
+    assert(revocation.isRevoked === false);
+
+    let reinstatedTwice = false;
+    try {
+      await program.methods
+        .reinstateLicense()
+        .accounts({
+          revocation: revocationPda,
+          listing: game.listing,
+          charter: charterPda,
+          authority: wallet.publicKey,
+        })
+        .rpc();
+      reinstatedTwice = true;
+    } catch (e) {
+      assert(e.error.errorCode.code === "LicenseNotRevoked");
+    }
+    assert(!reinstatedTwice);
+  });
+
+  it("Verifies license ownership", async () => {
+    const game = await listGame();
+    const purchase = await purchaseGame(game);
+    const [revocationPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("revocation"), purchase.listingTokenAccount.toBuffer()],
+      program.programId
+    );
+
+    const license = await program.methods
+      .verifyLicense(new anchor.BN(1))
+      .accounts({
+        listing: game.listing,
+        listingTokenAccount: purchase.listingTokenAccount,
+        holder: wallet.publicKey,
+        revocation: revocationPda,
+      })
+      .view();
+
+    console.log("  Valid license:", license.isValid);
+    assert(license.isValid === true);
+    assert(license.quantity.toNumber() === 1);
+    assert(license.isRevoked === false);
+
+    const stranger = await program.methods
+      .verifyLicense(new anchor.BN(1))
+      .accounts({
+        listing: game.listing,
+        listingTokenAccount: purchase.listingTokenAccount,
+        holder: Keypair.generate().publicKey,
+        revocation: revocationPda,
+      })
+      .view();
+    assert(stranger.isValid === false);
+  });
+
+  it("Opens and revokes a session key", async () => {
+    const game = await listGame({ consumable: true });
+    const purchase = await purchaseGame(game, { quantity: 5 });
+    const sessionKey = Keypair.generate();
+    const [sessionPda, sessionBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("session"), purchase.listingTokenAccount.toBuffer(), sessionKey.publicKey.toBuffer()],
+      program.programId
+    );
+
+    await program.methods
+      .createSession(sessionBump, 1, new anchor.BN(3), new anchor.BN(Math.floor(Date.now() / 1000) + 3600))
+      .accounts({
+        session: sessionPda,
+        listingTokenAccount: purchase.listingTokenAccount,
+        sessionKey: sessionKey.publicKey,
+        holder: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const session = await program.account.session.fetch(sessionPda);
+    console.log("  Session units:", session.maxUnits.toNumber());
+    assert(session.sessionKey.equals(sessionKey.publicKey));
+    assert(session.usedUnits.toNumber() === 0);
+
+    await program.methods
+      .revokeSession()
+      .accounts({ session: sessionPda, holder: wallet.publicKey })
+      .rpc();
+    assert((await provider.connection.getAccountInfo(sessionPda)) === null);
+  });
+
+  it("Consumes with a game server attestation", async () => {
+    const game = await listGame({ consumable: true });
+    const purchase = await purchaseGame(game, { quantity: 5 });
+    const server = Keypair.generate();
+
+    await program.methods
+      .setListingAttestationKey()
+      .accounts({
+        listing: game.listing,
+        attestationKey: server.publicKey,
+        user: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const amount = new anchor.BN(2);
+    const nonce = new anchor.BN(7);
+    const message = Buffer.concat([
+      Buffer.from("strangemood:consume"),
+      game.listing.toBuffer(),
+      wallet.publicKey.toBuffer(),
+      amount.toArrayLike(Buffer, "le", 8),
+      nonce.toArrayLike(Buffer, "le", 8),
+    ]);
+    const [noncePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("nonce"), game.listing.toBuffer(), wallet.publicKey.toBuffer(), nonce.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [, listingBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("listing"), game.mint.toBuffer()],
+      program.programId
+    );
+
+    const consume = (preInstructions = []) =>
+      program.methods
+        .consume(listingBump, game.mintBump, amount, nonce)
+        .accounts({
+          listing: game.listing,
+          mint: game.mint,
+          mintAuthority: game.mintAuthority,
+          listingTokenAccount: purchase.listingTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          session: null,
+          role: null,
+          consumedNonce: noncePda,
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          authority: wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .preInstructions([
+          ...preInstructions,
+          Ed25519Program.createInstructionWithPrivateKey({
+            privateKey: server.secretKey,
+            message,
+          }),
+        ])
+        .rpc();
+
+    await consume();
+    const token = await getAccount(provider.connection, purchase.listingTokenAccount);
+    console.log("  Units left:", Number(token.amount));
+    assert(Number(token.amount) === 3);
+    assert(token.isFrozen);
+
+    // A distinct transaction, so the replay reaches the nonce check.
+    let replayed = false;
+    try {
+      await consume([ComputeBudgetProgram.setComputeUnitLimit({ units: 300_000 })]);
+      replayed = true;
+    } catch (e) {
+      assert((e.logs || []).some((log) => log.includes("already in use")));
+    }
+    assert(!replayed);
+
+    // A holder consuming without a nonce can't spend the server's nonce 0.
+    const [zeroNonce] = PublicKey.findProgramAddressSync(
+      [Buffer.from("nonce"), game.listing.toBuffer(), wallet.publicKey.toBuffer(), Buffer.alloc(8)],
+      program.programId
+    );
+    let spentZero = false;
+    try {
+      await program.methods
+        .consume(listingBump, game.mintBump, new anchor.BN(0), null)
+        .accounts({
+          listing: game.listing,
+          mint: game.mint,
+          mintAuthority: game.mintAuthority,
+          listingTokenAccount: purchase.listingTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          session: null,
+          role: null,
+          consumedNonce: zeroNonce,
+          instructions: null,
+          authority: wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      spentZero = true;
+    } catch (e) {
+      assert(e.error.errorCode.code === "UnexpectedNonceAccount");
+    }
+    assert(!spentZero);
+    assert((await provider.connection.getAccountInfo(zeroNonce)) === null);
+  });
+
+  it("Purchases with a signed price voucher", async () => {
+    const game = await listGame({ price: 1_000_000 });
+    const storefront = Keypair.generate();
+
+    await program.methods
+      .setListingPricingKey()
+      .accounts({
+        listing: game.listing,
+        pricingKey: storefront.publicKey,
+        user: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const voucher = {
+      price: new anchor.BN(250_000),
+      expiresAt: new anchor.BN(Math.floor(Date.now() / 1000) + 600),
+      nonce: new anchor.BN(42),
+      signer: storefront,
+    };
+    const purchase = await purchaseGame(game, { quantity: 2, voucher });
+    const receipt = await program.account.receipt.fetch(purchase.receipt);
+    const escrow = await getAccount(provider.connection, purchase.escrow);
+    console.log("  Voucher price:", receipt.price.toNumber() / 1e6, "USDC");
+    assert(receipt.price.toNumber() === 250_000);
+    assert(Number(escrow.amount) === 500_000);
+
+    let reused = false;
+    try {
+      await purchaseGame(game, { voucher });
+      reused = true;
+    } catch (e) {}
+    assert(!reused);
+
+    // A purchase without a voucher can't spend the pricing key's nonce 0.
+    const [zeroNonce] = PublicKey.findProgramAddressSync(
+      [Buffer.from("voucher"), game.listing.toBuffer(), Buffer.alloc(8)],
+      program.programId
+    );
+    let spentZero = false;
+    try {
+      await purchaseGame(game, { voucherNonce: zeroNonce });
+      spentZero = true;
+    } catch (e) {
+      assert(e.error.errorCode.code === "UnexpectedNonceAccount");
+    }
+    assert(!spentZero);
+    assert((await provider.connection.getAccountInfo(zeroNonce)) === null);
+  });
+
+  it("Redeems a key code", async () => {
+    const game = await listGame();
+    // Each code seeds an ed25519 key; only that key is committed and revealed.
+    const codeKeys = ["PRESS-0001", "PRESS-0002", "PRESS-0003"].map((c) =>
+      Keypair.fromSeed(sha256(Buffer.from(c)))
+    );
+    const leaf = (index, codeKey) => {
+      const indexBytes = Buffer.alloc(4);
+      indexBytes.writeUInt32LE(index);
+      return sha256(indexBytes, codeKey.publicKey.toBuffer());
+    };
+    const tree = merkleTree(codeKeys.map((codeKey, i) => leaf(i, codeKey)));
+
+    const batchId = new anchor.BN(1);
+    const [codeBatch, codeBatchBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("codes"), game.listing.toBuffer(), batchId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    await program.methods
+      .initCodeBatch(codeBatchBump, batchId, Array.from(tree.root), codeKeys.length)
+      .accounts({
+        codeBatch,
+        listing: game.listing,
+        charter: charterPda,
+        charterTreasury: null,
+        charterTreasuryDeposit: null,
+        listingPaymentDeposit: null,
+        feeTokenAccount: null,
+        user: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const license = await createAccount(
+      provider.connection,
+      wallet.payer,
+      game.mint,
+      wallet.publicKey,
+      Keypair.generate(),
+      CONFIRM_OPTS
+    );
+    const redeem = (index, preInstructions = []) => {
+      const indexBytes = Buffer.alloc(4);
+      indexBytes.writeUInt32LE(index);
+      return program.methods
+        .redeemCode(game.mintBump, index, codeKeys[index].publicKey, tree.proofs[index].map((p) => Array.from(p)))
+        .accounts({
+          codeBatch,
+          listing: game.listing,
+          listingMint: game.mint,
+          listingMintAuthority: game.mintAuthority,
+          listingTokenAccount: license,
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          redeemer: wallet.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .preInstructions([
+          ...preInstructions,
+          Ed25519Program.createInstructionWithPrivateKey({
+            privateKey: codeKeys[index].secretKey,
+            message: Buffer.concat([
+              Buffer.from("strangemood:redeem"),
+              codeBatch.toBuffer(),
+              indexBytes,
+              wallet.publicKey.toBuffer(),
+            ]),
+          }),
+        ])
+        .rpc();
+    };
+
+    await redeem(1);
+    let token = await getAccount(provider.connection, license);
+    const batch = await program.account.codeBatch.fetch(codeBatch);
+    console.log("  Codes redeemed:", batch.redeemed, "of", batch.size);
+    assert(Number(token.amount) === 1 && token.isFrozen);
+    assert(batch.redeemed === 1);
+
+    let redeemedTwice = false;
+    try {
+      await redeem(1, [ComputeBudgetProgram.setComputeUnitLimit({ units: 300_000 })]);
+      redeemedTwice = true;
+    } catch (e) {
+      assert(e.error.errorCode.code === "CodeAlreadyRedeemed");
+    }
+    assert(!redeemedTwice);
+
+    // A second code lands in the same, already frozen, license account.
+    await redeem(2);
+    token = await getAccount(provider.connection, license);
+    assert(Number(token.amount) === 2 && token.isFrozen);
+  });
+
+  it("Claims a license from an airdrop distributor", async () => {
+    const game = await listGame();
+    const allocations = [
+      { wallet: Keypair.generate().publicKey, quantity: 1 },
+      { wallet: wallet.publicKey, quantity: 2 },
+      { wallet: Keypair.generate().publicKey, quantity: 1 },
+    ];
+    const leaf = (index, { wallet, quantity }) => {
+      const indexBytes = Buffer.alloc(4);
+      indexBytes.writeUInt32LE(index);
+      return sha256(indexBytes, wallet.toBuffer(), game.listing.toBuffer(), new anchor.BN(quantity).toArrayLike(Buffer, "le", 8));
+    };
+    const tree = merkleTree(allocations.map((a, i) => leaf(i, a)));
+
+    const distributorId = new anchor.BN(1);
+    const [distributor, distributorBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("distributor"), game.listing.toBuffer(), distributorId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    await program.methods
+      .initDistributor(distributorBump, distributorId, Array.from(tree.root), allocations.length)
+      .accounts({
+        distributor,
+        listing: game.listing,
+        user: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const license = await createAccount(
+      provider.connection,
+      wallet.payer,
+      game.mint,
+      wallet.publicKey,
+      Keypair.generate(),
+      CONFIRM_OPTS
+    );
+    const claim = () =>
+      program.methods
+        .claimLicense(game.mintBump, 1, new anchor.BN(2), tree.proofs[1].map((p) => Array.from(p)))
+        .accounts({
+          distributor,
+          listing: game.listing,
+          listingMint: game.mint,
+          listingMintAuthority: game.mintAuthority,
+          listingTokenAccount: license,
+          claimant: wallet.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+    await claim();
+    const token = await getAccount(provider.connection, license);
+    const state = await program.account.distributor.fetch(distributor);
+    console.log("  Claimed:", state.claimed, "of", state.maxClaims);
+    assert(Number(token.amount) === 2 && token.isFrozen);
+    assert(state.claimedQuantity.toNumber() === 2);
+
+    let claimedTwice = false;
+    try {
+      await claim();
+      claimedTwice = true;
+    } catch (e) {}
+    assert(!claimedTwice);
+  });
+
+  it("Changes the charter through a governance proposal", async () => {
+    const { charter, mint, voteDeposit } = await createCharter();
+    await mintTo(provider.connection, wallet.payer, mint, voteDeposit, wallet.publicKey, 150, [], CONFIRM_OPTS);
+
+    const [governance, governanceBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("governance"), charter.toBuffer()],
+      program.programId
+    );
+    const [vault] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), governance.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .initGovernance(governanceBump, new anchor.BN(100), 5000, new anchor.BN(2))
+      .accounts({
+        governance,
+        vault,
+        charter,
+        charterMint: mint,
+        authority: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        rent: SYSVAR_RENT_PUBKEY,
+      })
+      .rpc();
+    await changeCharter(charter, (pending, pendingBump) =>
+      program.methods
+        .setCharterAuthority(pendingBump)
+        .accounts({ charter, pending, authority: governance, user: wallet.publicKey, systemProgram: SystemProgram.programId })
+    );
+    await program.methods.acceptCharterAuthority().accounts({ charter, governance, authority: null }).rpc();
+
+    const [proposal, proposalBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("proposal"), governance.toBuffer(), new anchor.BN(0).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    await program.methods
+      .createProposal(proposalBump, { expansionRate: { amount: new anchor.BN(3), decimals: 0 } }, "ipfs://proposal")
+      .accounts({ proposal, governance, proposer: wallet.publicKey, systemProgram: SystemProgram.programId })
+      .rpc();
+
+    const [vote, voteBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vote"), proposal.toBuffer(), wallet.publicKey.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .castVote(voteBump, new anchor.BN(150), true)
+      .accounts({
+        vote,
+        proposal,
+        governance,
+        vault,
+        voterTokenAccount: voteDeposit,
+        voter: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+
+    await new Promise((resolve) => setTimeout(resolve, 3000));
+    await changeCharter(charter, (pending, pendingBump) =>
+      program.methods
+        .executeProposal(pendingBump)
+        .accounts({ proposal, governance, charter, pending, executor: wallet.publicKey, systemProgram: SystemProgram.programId })
+    );
+    const state = await program.account.charter.fetch(charter);
+    console.log("  Expansion rate after proposal:", state.expansionRateAmount.toNumber());
+    assert(state.expansionRateAmount.toNumber() === 3);
+
+    await program.methods
+      .withdrawVote(governanceBump)
+      .accounts({
+        vote,
+        proposal,
+        governance,
+        vault,
+        voterTokenAccount: voteDeposit,
+        voter: wallet.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+    const returned = await getAccount(provider.connection, voteDeposit);
+    assert(Number(returned.amount) === 150);
+  });
+
+  it("Delays and cancels a charter change", async () => {
+    const { charter } = await createCharter();
+    await changeCharter(charter, (pending, pendingBump) =>
+      program.methods
+        .setCharterChangeDelay(pendingBump, new anchor.BN(3600))
+        .accounts({ charter, pending, user: wallet.publicKey, systemProgram: SystemProgram.programId })
+    );
+
+    const [pending, pendingBump] = await nextPendingChange(charter);
+    await program.methods
+      .setCharterContributionRate(pendingBump, new anchor.BN(50), 2, new anchor.BN(50), 2)
+      .accounts({ charter, pending, user: wallet.publicKey, systemProgram: SystemProgram.programId })
+      .rpc();
+    const queued = await program.account.pendingCharterChange.fetch(pending);
+    console.log("  Effective in:", queued.effectiveAt.sub(queued.queuedAt).toNumber(), "seconds");
+    assert(queued.effectiveAt.sub(queued.queuedAt).toNumber() === 3600);
+
+    let appliedEarly = false;
+    try {
+      await program.methods
+        .applyCharterChange()
+        .accounts({ pending, charter, treasury: null, governance: null, payer: wallet.publicKey })
+        .rpc();
+      appliedEarly = true;
+    } catch (e) {}
+    assert(!appliedEarly);
+
+    await program.methods
+      .cancelCharterChange()
+      .accounts({ pending, charter, payer: wallet.publicKey, user: wallet.publicKey })
+      .rpc();
+    const state = await program.account.charter.fetch(charter);
+    assert(state.paymentContributionRateAmount.toNumber() === 10);
+    assert((await provider.connection.getAccountInfo(pending)) === null);
+
+    // Without a delay the change is due at once, so it can no longer be cancelled.
+    const { charter: undelayed } = await createCharter();
+    const [due, dueBump] = await nextPendingChange(undelayed);
+    await program.methods
+      .setCharterContributionRate(dueBump, new anchor.BN(50), 2, new anchor.BN(50), 2)
+      .accounts({ charter: undelayed, pending: due, user: wallet.publicKey, systemProgram: SystemProgram.programId })
+      .rpc();
+    let cancelledLate = false;
+    try {
+      await program.methods
+        .cancelCharterChange()
+        .accounts({ pending: due, charter: undelayed, payer: wallet.publicKey, user: wallet.publicKey })
+        .rpc();
+      cancelledLate = true;
+    } catch (e) {
+      assert(e.error.errorCode.code === "ChangeAlreadyEffective");
+    }
+    assert(!cancelledLate);
+  });
+
+  it("Holds charter changes to the charter's caps", async () => {
+    const { charter } = await createCharter();
+    await program.methods
+      .setCharterCaps(1500, 10_000, 10_000, new anchor.BN(0))
+      .accounts({ charter, user: wallet.publicKey })
+      .rpc();
+
+    const queueRate = async (amount) => {
+      const [pending, pendingBump] = await nextPendingChange(charter);
+      return program.methods
+        .setCharterContributionRate(pendingBump, new anchor.BN(amount), 2, new anchor.BN(10), 2)
+        .accounts({ charter, pending, user: wallet.publicKey, systemProgram: SystemProgram.programId })
+        .rpc();
+    };
+    const rejects = async (promise) => {
+      try {
+        await promise;
+        return false;
+      } catch (e) {
+        return true;
+      }
+    };
+
+    assert(await rejects(queueRate(20)));
+    assert(await rejects(queueRate(120)));
+    assert(
+      await rejects(
+        program.methods
+          .setCharterCaps(2000, 10_000, 10_000, new anchor.BN(0))
+          .accounts({ charter, user: wallet.publicKey })
+          .rpc()
+      )
+    );
+
+    await changeCharter(charter, (pending, pendingBump) =>
+      program.methods
+        .setCharterContributionRate(pendingBump, new anchor.BN(15), 2, new anchor.BN(10), 2)
+        .accounts({ charter, pending, user: wallet.publicKey, systemProgram: SystemProgram.programId })
+    );
+    const state = await program.account.charter.fetch(charter);
+    console.log("  Payment contribution cap:", state.paymentContributionCapBps / 100 + "%");
+    assert(state.paymentContributionRateAmount.toNumber() === 15);
+  });
+
+  it("Hands a listing to a new authority in two steps", async () => {
+    const game = await listGame();
+    const publisher = Keypair.generate();
+    const nominate = () =>
+      program.methods
+        .setListingAuthority()
+        .accounts({
+          listing: game.listing,
+          authority: publisher.publicKey,
+          user: wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+    await nominate();
+    let listing = await program.account.listing.fetch(game.listing);
+    assert(listing.authority.equals(wallet.publicKey));
+    assert(listing.pendingAuthority.equals(publisher.publicKey));
+
+    await program.methods
+      .cancelListingAuthority()
+      .accounts({ listing: game.listing, user: wallet.publicKey })
+      .rpc();
+    listing = await program.account.listing.fetch(game.listing);
+    assert(listing.pendingAuthority.equals(PublicKey.default));
+
+    await nominate();
+    await program.methods
+      .acceptListingAuthority()
+      .accounts({ listing: game.listing, authority: publisher.publicKey })
+      .signers([publisher])
+      .rpc();
+    listing = await program.account.listing.fetch(game.listing);
+    console.log("  New listing authority:", listing.authority.toBase58());
+    assert(listing.authority.equals(publisher.publicKey));
+    assert(listing.pendingAuthority.equals(PublicKey.default));
+  });
+
+  it("Controls a listing with a 2-of-2 multisig", async () => {
+    const cosigner = Keypair.generate();
+    const createKey = Keypair.generate();
+    const [multisig, multisigBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("multisig"), createKey.publicKey.toBuffer()],
+      program.programId
+    );
+    const [signer, signerBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("signer"), multisig.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .createMultisig(multisigBump, [wallet.publicKey, cosigner.publicKey], 2)
+      .accounts({ multisig, createKey: createKey.publicKey, payer: wallet.publicKey, systemProgram: SystemProgram.programId })
+      .signers([createKey])
+      .rpc();
+
+    // Proposes `ix`, has the cosigner approve it, then executes it.
+    const run = async (ix) => {
+      const { transactionCount } = await program.account.multisig.fetch(multisig);
+      const [transaction, transactionBump] = PublicKey.findProgramAddressSync(
+        [Buffer.from("transaction"), multisig.toBuffer(), transactionCount.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+      await program.methods
+        .proposeTransaction(transactionBump, ix.keys, ix.data)
+        .accounts({ transaction, multisig, proposer: wallet.publicKey, systemProgram: SystemProgram.programId })
+        .rpc();
+      await program.methods
+        .approveTransaction()
+        .accounts({ transaction, multisig, member: cosigner.publicKey })
+        .signers([cosigner])
+        .rpc();
+      await program.methods
+        .executeTransaction(signerBump)
+        .accounts({ transaction, multisig, signer, member: wallet.publicKey })
+        .remainingAccounts([
+          ...ix.keys.map((k) => ({ ...k, isSigner: k.isSigner && !k.pubkey.equals(signer) })),
+          { pubkey: program.programId, isSigner: false, isWritable: false },
+        ])
+        .rpc();
+    };
+
+    const game = await listGame();
+    await program.methods
+      .setListingAuthority()
+      .accounts({ listing: game.listing, authority: signer, user: wallet.publicKey, systemProgram: SystemProgram.programId })
+      .rpc();
+    await run(
+      await program.methods.acceptListingAuthority().accounts({ listing: game.listing, authority: signer }).instruction()
+    );
+    await run(
+      await program.methods
+        .setListingPrice(new anchor.BN(2_500_000))
+        .accounts({ listing: game.listing, role: null, user: signer, systemProgram: SystemProgram.programId })
+        .instruction()
+    );
+
+    const listing = await program.account.listing.fetch(game.listing);
+    console.log("  Listing authority:", listing.authority.toBase58());
+    assert(listing.authority.equals(signer));
+    assert(listing.price.toNumber() === 2_500_000);
+  });
+
+  it("Delegates listing pricing to a role", async () => {
+    const game = await listGame();
+    const pricing = Keypair.generate();
+    const [role, roleBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("role"), game.listing.toBuffer(), pricing.publicKey.toBuffer()],
+      program.programId
+    );
+    const ROLE_PRICE_MANAGER = 1;
+
+    await program.methods
+      .grantListingRole(roleBump, ROLE_PRICE_MANAGER)
+      .accounts({
+        role,
+        listing: game.listing,
+        grantee: pricing.publicKey,
+        user: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const setPrice = (price) =>
+      program.methods
+        .setListingPrice(new anchor.BN(price))
+        .accounts({ listing: game.listing, role, user: pricing.publicKey, systemProgram: SystemProgram.programId })
+        .signers([pricing])
+        .rpc();
+    const setUri = () =>
+      program.methods
+        .setListingUri("ipfs://defaced")
+        .accounts({ listing: game.listing, role, user: pricing.publicKey, systemProgram: SystemProgram.programId })
+        .signers([pricing])
+        .rpc();
+
+    await setPrice(800_000);
+    const listing = await program.account.listing.fetch(game.listing);
+    console.log("  Price set by role:", listing.price.toNumber() / 1e6, "USDC");
+    assert(listing.price.toNumber() === 800_000);
+
+    let editedUri = false;
+    try {
+      await setUri();
+      editedUri = true;
+    } catch (e) {}
+    assert(!editedUri);
+
+    await program.methods
+      .revokeListingRole(ROLE_PRICE_MANAGER)
+      .accounts({ role, listing: game.listing, user: wallet.publicKey })
+      .rpc();
+    let pricedAfterRevoke = false;
+    try {
+      await setPrice(1);
+      pricedAfterRevoke = true;
+    } catch (e) {}
+    assert(!pricedAfterRevoke);
+  });
+
+  it("Delegates cashing, support and consumption to roles", async () => {
+    const game = await listGame({ refundable: false, consumable: true });
+    const staff = Keypair.generate();
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        SystemProgram.transfer({ fromPubkey: wallet.publicKey, toPubkey: staff.publicKey, lamports: 100_000_000 })
+      )
+    );
+    const [role, roleBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("role"), game.listing.toBuffer(), staff.publicKey.toBuffer()],
+      program.programId
+    );
+    const ROLE_SUPPORT = 1 << 2;
+    const ROLE_CASHIER = 1 << 3;
+    const ROLE_CONSUMER = 1 << 4;
+    await program.methods
+      .grantListingRole(roleBump, ROLE_SUPPORT | ROLE_CASHIER | ROLE_CONSUMER)
+      .accounts({
+        role,
+        listing: game.listing,
+        grantee: staff.publicKey,
+        user: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    // Cashier: cash a receipt that names some other cashier.
+    const cashed = await purchaseGame(game);
+    await cashGame(game, cashed, { cashier: staff, role });
+    let license = await getAccount(provider.connection, cashed.listingTokenAccount);
+    assert(Number(license.amount) === 1);
+
+    // Support: refund a purchase on the publisher's behalf.
+    const supportRefund = (purchase) =>
+      program.methods
+        .refund(game.mintBump, purchase.escrowBump)
+        .accounts({
+          authority: staff.publicKey,
+          role,
+          purchaser: wallet.publicKey,
+          returnDeposit: purchase.purchaseTokenAccount,
+          receipt: purchase.receipt,
+          escrow: purchase.escrow,
+          escrowAuthority: purchase.escrowAuthority,
+          listingTokenAccount: purchase.listingTokenAccount,
+          listing: game.listing,
+          charter: charterPda,
+          campaign: null,
+          listingMint: game.mint,
+          listingMintAuthority: game.mintAuthority,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([staff])
+        .rpc();
+    const refunded = await purchaseGame(game);
+    await supportRefund(refunded);
+    assert((await provider.connection.getAccountInfo(refunded.receipt)) === null);
+
+    // Consumer: attest a consume, like a game server.
+    const [, listingBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("listing"), game.mint.toBuffer()],
+      program.programId
+    );
+    const amount = new anchor.BN(1);
+    const nonce = new anchor.BN(11);
+    const [consumedNonce] = PublicKey.findProgramAddressSync(
+      [Buffer.from("nonce"), game.listing.toBuffer(), wallet.publicKey.toBuffer(), nonce.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    await program.methods
+      .consume(listingBump, game.mintBump, amount, nonce)
+      .accounts({
+        listing: game.listing,
+        mint: game.mint,
+        mintAuthority: game.mintAuthority,
+        listingTokenAccount: cashed.listingTokenAccount,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        session: null,
+        role,
+        consumedNonce,
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+        authority: wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .preInstructions([
+        Ed25519Program.createInstructionWithPrivateKey({
+          privateKey: staff.secretKey,
+          message: Buffer.concat([
+            Buffer.from("strangemood:consume"),
+            game.listing.toBuffer(),
+            wallet.publicKey.toBuffer(),
+            amount.toArrayLike(Buffer, "le", 8),
+            nonce.toArrayLike(Buffer, "le", 8),
+          ]),
+        }),
+      ])
+      .rpc();
+    license = await getAccount(provider.connection, cashed.listingTokenAccount);
+    assert(Number(license.amount) === 0);
+
+    // Revoke: a revoked cashier can no longer cash.
+    await program.methods
+      .revokeListingRole(ROLE_CASHIER)
+      .accounts({ role, listing: game.listing, user: wallet.publicKey })
+      .rpc();
+    let cashedAfterRevoke = false;
+    try {
+      await cashGame(game, await purchaseGame(game), { cashier: staff, role });
+      cashedAfterRevoke = true;
+    } catch (e) {
+      assert(e.error.errorCode.code === "OnlyCashableByTheCashier");
+    }
+    assert(!cashedAfterRevoke);
+
+    // Handover: roles granted by the previous authority lapse.
+    const successor = Keypair.generate();
+    await program.methods
+      .setListingAuthority()
+      .accounts({ listing: game.listing, authority: successor.publicKey, user: wallet.publicKey, systemProgram: SystemProgram.programId })
+      .rpc();
+    await program.methods
+      .acceptListingAuthority()
+      .accounts({ listing: game.listing, authority: successor.publicKey })
+      .signers([successor])
+      .rpc();
+    let supportedAfterHandover = false;
+    try {
+      await supportRefund(await purchaseGame(game));
+      supportedAfterHandover = true;
+    } catch (e) {
+      assert(e.error.errorCode.code === "UnauthorizedAuthority");
+    }
+    assert(!supportedAfterHandover);
+  });
+
+  it("Withdraws from the treasury within its epoch limit", async () => {
+    const [, treasuryBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("treasury"), charterPda.toBuffer(), paymentMint.toBuffer()],
+      program.programId
+    );
+    const destination = await createAccount(
+      provider.connection,
+      wallet.payer,
+      paymentMint,
+      wallet.publicKey,
+      Keypair.generate(),
+      CONFIRM_OPTS
+    );
+    const withdraw = (amount) =>
+      program.methods
+        .withdrawFromTreasury(treasuryBump, new anchor.BN(amount))
+        .accounts({
+          treasury: treasuryPda,
+          charter: charterPda,
+          deposit: treasuryDeposit,
+          destination,
+          pending: null,
+          payer: null,
+          user: wallet.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+    await withdraw(1_000);
+    const received = await getAccount(provider.connection, destination);
+    const treasury = await program.account.charterTreasury.fetch(treasuryPda);
+    console.log("  Withdrawn this epoch:", treasury.epochSpent.toNumber() / 1e6, "USDC");
+    assert(Number(received.amount) === 1_000);
+    assert(treasury.epochSpent.toNumber() === 1_000);
+
+    let overspent = false;
+    try {
+      await withdraw(1_000_000);
+      overspent = true;
+    } catch (e) {}
+    assert(!overspent);
+  });
+
+  it("Vests a listing's minted votes", async () => {
+    const setVesting = (cliff, duration) =>
+      changeCharter(charterPda, (pending, pendingBump) =>
+        program.methods
+          .setCharterVoteVesting(pendingBump, new anchor.BN(cliff), new anchor.BN(duration))
+          .accounts({ charter: charterPda, pending, user: wallet.publicKey, systemProgram: SystemProgram.programId })
+      );
+    await setVesting(3_600, 86_400);
+
+    const game = await listGame({ refundable: false });
+    const purchase = await purchaseGame(game, { cashier: wallet.publicKey });
+    const { vesting, vestingVault } = await cashGame(game, purchase);
+
+    const escrowed = await program.account.voteVesting.fetch(vesting);
+    const vault = await getAccount(provider.connection, vestingVault);
+    console.log("  Votes vesting:", escrowed.locked.toNumber());
+    assert(escrowed.locked.toNumber() > 0);
+    assert(Number(vault.amount) === escrowed.locked.toNumber());
+    assert(escrowed.mint.equals(charterMint));
+
+    const [, vestingBump] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vesting"), game.listing.toBuffer(), charterMint.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .claimVestedVotes(vestingBump)
+      .accounts({
+        vesting,
+        vault: vestingVault,
+        listing: game.listing,
+        listingVoteDeposit: game.voteDeposit,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+    const claimed = await getAccount(provider.connection, game.voteDeposit);
+    assert(Number(claimed.amount) === 0);
+
+    await setVesting(0, 0);
+  });
+
+  it("Clamps vote minting to the charter's emission caps", async () => {
+    const U64_MAX = new anchor.BN("18446744073709551615");
+    const setEmissionPolicy = (decayBps, epochDuration, epochCap, lifetimeCap) =>
+      changeCharter(charterPda, (pending, pendingBump) =>
+        program.methods
+          .setCharterEmissionPolicy(pendingBump, decayBps, new anchor.BN(epochDuration), epochCap, lifetimeCap)
+          .accounts({ charter: charterPda, pending, user: wallet.publicKey, systemProgram: SystemProgram.programId })
+      );
+    const [emissions] = PublicKey.findProgramAddressSync(
+      [Buffer.from("emissions"), charterPda.toBuffer()],
+      program.programId
+    );
+    const before = await program.account.charterEmissions.fetchNullable(emissions);
+    const mintedBefore = before ? before.epochMinted : new anchor.BN(0);
+    await setEmissionPolicy(5_000, 0, mintedBefore.addn(1_000), U64_MAX);
+
+    const game = await listGame({ refundable: false });
+    await cashGame(game, await purchaseGame(game, { cashier: wallet.publicKey }));
+    let accounting = await program.account.charterEmissions.fetch(emissions);
+    assert(accounting.epochMinted.sub(mintedBefore).toNumber() === 1_000);
+
+    // The cap is spent, so the next sale settles without minting.
+    await cashGame(game, await purchaseGame(game, { cashier: wallet.publicKey }));
+    accounting = await program.account.charterEmissions.fetch(emissions);
+    const deposit = await getAccount(provider.connection, game.paymentDeposit);
+    console.log("  Votes minted this epoch:", accounting.epochMinted.toString());
+    assert(accounting.epochMinted.sub(mintedBefore).toNumber() === 1_000);
+    assert(Number(deposit.amount) === 2 * 900_000);
+
+    await setEmissionPolicy(10_000, 0, U64_MAX, U64_MAX);
+  });
+
+  it("Splits minted votes between buyer, listing and charter", async () => {
+    const setBuyerVoteRate = (amount, decimals) =>
+      changeCharter(charterPda, (pending, pendingBump) =>
+        program.methods
+          .setCharterBuyerVoteRate(pendingBump, new anchor.BN(amount), decimals)
+          .accounts({ charter: charterPda, pending, user: wallet.publicKey, systemProgram: SystemProgram.programId })
+      );
+    await setBuyerVoteRate(20, 2);
+
+    const player = Keypair.generate();
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        SystemProgram.transfer({ fromPubkey: wallet.publicKey, toPubkey: player.publicKey, lamports: 100_000_000 })
+      )
+    );
+    const purchaserVoteDeposit = await createAccount(
+      provider.connection,
+      wallet.payer,
+      charterMint,
+      player.publicKey,
+      Keypair.generate(),
+      CONFIRM_OPTS
+    );
+    const charterVotesBefore = Number((await getAccount(provider.connection, charterVoteDeposit)).amount);
+
+    // A 1 USDC sale contributes 0.1 USDC, which mints 100,000 votes.
+    const game = await listGame({ refundable: false });
+    const purchase = await purchaseGame(game, { cashier: wallet.publicKey, buyer: player });
+    const { vestingVault } = await cashGame(game, purchase, { purchaserVoteDeposit });
+
+    const buyer = Number((await getAccount(provider.connection, purchaserVoteDeposit)).amount);
+    const listing = Number((await getAccount(provider.connection, vestingVault)).amount);
+    const charter = Number((await getAccount(provider.connection, charterVoteDeposit)).amount) - charterVotesBefore;
+    console.log("  Votes to buyer / listing / charter:", buyer, "/", listing, "/", charter);
+    assert(buyer === 20_000);
+    assert(listing === 72_000);
+    assert(charter === 8_000);
+
+    let cashedWithoutBuyer = false;
+    try {
+      await cashGame(game, await purchaseGame(game, { cashier: wallet.publicKey, buyer: player }));
+      cashedWithoutBuyer = true;
+    } catch (e) {}
+    assert(!cashedWithoutBuyer);
+
+    // The listing authority buying its own game earns no buyer share, so
+    // all 100,000 votes split between the listing and the charter.
+    const listingBefore = Number((await getAccount(provider.connection, vestingVault)).amount);
+    await cashGame(game, await purchaseGame(game, { cashier: wallet.publicKey }));
+    const listingAfter = Number((await getAccount(provider.connection, vestingVault)).amount);
+    assert(listingAfter - listingBefore === 90_000);
+
+    await setBuyerVoteRate(0, 0);
+  });
+
+  it("Updates charter contribution rates", async () => {
+    await changeCharter(charterPda, (pending, pendingBump) =>
+      program.methods
+        .setCharterContributionRate(pendingBump, new anchor.BN(15), 2, new anchor.BN(15), 2)
+        .accounts({
+          charter: charterPda,
+          pending,
+          user: wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+    );
+
     const charter = await program.account.charter.fetch(charterPda);
     console.log("  New payment rate:", charter.paymentContributionRateAmount.toNumber() + "%");
     assert(charter.paymentContributionRateAmount.toNumber() === 15);